@@ -1,9 +1,12 @@
-use slugify_filenames::{Result, SlugifyFilenames};
+use slugify_filenames::SlugifyFilenames;
 
-fn main() -> Result<()> {
-    Ok(SlugifyFilenames::execute(
+fn main() {
+    if let Err(error) = SlugifyFilenames::execute(
         std::env::args()
             .map(|c| c.to_string())
             .collect::<Vec<String>>(),
-    )?)
+    ) {
+        eprintln!("{error}");
+        std::process::exit(error.exit_code());
+    }
 }
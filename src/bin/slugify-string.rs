@@ -1,7 +1,8 @@
-use slugify_filenames::{Result, SlugifyString};
+use slugify_filenames::SlugifyString;
 
-fn main() -> Result<()> {
-    Ok(SlugifyString::execute(
-        std::env::args().map(|c| c.to_string()).collect(),
-    )?)
+fn main() {
+    if let Err(error) = SlugifyString::execute(std::env::args().map(|c| c.to_string()).collect()) {
+        eprintln!("{error}");
+        std::process::exit(error.exit_code());
+    }
 }
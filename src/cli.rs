@@ -10,3 +10,56 @@ pub use aliasing::heck_aliases;
 
 pub mod verbosity;
 pub use verbosity::Verbosity;
+
+use crate::config::Config;
+use crate::errors::{Error, Result};
+use iocore::Path;
+
+/// `parse` parses `args` into `T`, failing with [`Error::UsageError`]
+/// on invalid arguments, `--help` and `--version` are printed and exit
+/// right away as usual.
+pub fn parse<T: clap::Parser>(args: Vec<String>) -> Result<T> {
+    T::try_parse_from(args).map_err(|error| {
+        if !error.use_stderr() {
+            error.exit()
+        }
+        Error::from(error)
+    })
+}
+
+/// `parse_configured` parses `args` into `T` with the settings of the
+/// configuration files as defaults: the file given with `--config`, or
+/// else the discovered ones, see [`Config::discover`], along with the
+/// settings of `--preset`, see [`Config::with_preset`]. Invalid
+/// arguments are reported as [`Error::UsageError`] before any file is
+/// read.
+pub fn parse_configured<T: clap::Parser>(args: Vec<String>) -> Result<(T, Config)> {
+    let matches = T::command().try_get_matches_from(&args).map_err(|error| {
+        if !error.use_stderr() {
+            error.exit()
+        }
+        Error::from(error)
+    })?;
+    let config = match matches.get_one::<Path>("config") {
+        Some(path) => Config::load(path)?,
+        None => Config::discover(&Path::cwd())?,
+    }
+    .with_preset(matches.get_one::<String>("preset").map(String::as_str))?;
+    let matches = config.matches(T::command(), &args)?;
+    Ok((T::from_arg_matches(&matches)?, config))
+}
+
+/// `print_config` prints the settings of `T` in effect after parsing
+/// `args` with `config`, in the format of configuration files
+pub fn print_config<T: clap::Parser>(config: &Config, args: &[String]) -> Result<()> {
+    for file in config.files() {
+        println!("# {file}");
+    }
+    let effective = config.effective(T::command(), args)?;
+    print!(
+        "{}",
+        toml::to_string(&effective)
+            .map_err(|error| Error::SerializationError(error.to_string()))?
+    );
+    Ok(())
+}
@@ -1,12 +1,19 @@
 use crate::cli::parameters::SlugifyParameters;
 use crate::cli::verbosity::Verbosity;
-use std::time::SystemTime;
-use heck::ToPascalCase;
-use heck::ToShoutySnakeCase;
-use heck::ToShoutyKebabCase;
 pub use crate::errors::{Error, Result};
+use crate::executor::{Executor, RenameOutcome};
+use crate::filesystem::{Filesystem, RealFilesystem};
+use crate::git::GitFilesystem;
+use crate::ignore::{IgnoreRules, IgnoreStack, SLUGIFY_IGNORE_FILENAME, git_work_tree};
+use crate::journal::{DEFAULT_JOURNAL_FILENAME, Journal, JournalEntry};
+use crate::plan::{ConflictStrategy, RenameOperation, RenamePlan, RenameReason};
+use crate::planner::{DEFAULT_SUFFIX_FORMAT, Planner};
+use crate::references::ReferenceRewriter;
+use crate::report::{OutputFormat, RenameRecord};
 use clap::{ArgAction, Parser};
 use iocore::Path;
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::SystemTime;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -19,9 +26,19 @@ pub struct SlugifyFilenames {
     #[arg()]
     paths: Vec<Path>,
 
-    #[arg(short, long)]
+    #[arg(
+        short,
+        long,
+        help = "rename at most this many levels below each given path, implies -r/--recursive"
+    )]
     max_depth: Option<usize>,
 
+    #[arg(
+        long,
+        help = "do not rename entries less than this many levels below each given path, implies -r/--recursive"
+    )]
+    min_depth: Option<usize>,
+
     #[command(flatten)]
     parameters: SlugifyParameters,
 
@@ -34,21 +51,86 @@ pub struct SlugifyFilenames {
     #[arg(long, default_value = "info", conflicts_with_all=["quiet", "verbose"], help = "set verbosity explicitly rather than by increments or decrements (.i.e.: `--verbose' or `--quiet')")]
     verbosity: Verbosity,
 
-    #[arg(short, long)]
+    #[arg(
+        short,
+        long,
+        help = "bypass ignore rules and overwrite targets, implies --on-conflict merge unless given"
+    )]
     force: bool,
 
+    #[arg(
+        long,
+        value_enum,
+        help = "what to do when the slugified name is taken [default: suffix]"
+    )]
+    on_conflict: Option<ConflictStrategy>,
+
+    #[arg(
+        long,
+        default_value = DEFAULT_SUFFIX_FORMAT,
+        help = "suffix appended to taken names with --on-conflict suffix, {n} is replaced by a counter, e.g.: \"-{n}\" or \"_({n})\""
+    )]
+    suffix_format: String,
+
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "first value of the counter of --suffix-format"
+    )]
+    suffix_start: usize,
+
+    #[arg(
+        short,
+        long,
+        help = "carry on past entries that cannot be renamed, summarize them at the end and exit with status 4"
+    )]
+    keep_going: bool,
+
+    #[arg(
+        long,
+        value_name = "GLOB",
+        help = "rewrite the references to renamed entries, e.g.: links, inside the files below the given paths (their git working trees with --git) matching GLOB, printing diffs instead with --dry-run"
+    )]
+    rewrite_references: Vec<String>,
+
     #[arg(long)]
     debug: bool,
 
     #[arg(short, long)]
     dry_run: bool,
 
+    #[arg(
+        long,
+        conflicts_with_all = ["undo"],
+        help = "print every entry whose name is not a slug along with its suggested name without renaming anything, exiting with status 2 if there is any"
+    )]
+    check: bool,
+
     #[arg(short, long)]
     recursive: bool,
 
+    #[arg(
+        long,
+        help = "rename entries tracked by git as `git mv` does so that history follows them, refusing entries with uncommitted modifications unless --force"
+    )]
+    git: bool,
+
     #[arg(short = 'I', long, help = "path to .slugifyignore file")]
     slugify_ignore: Option<Path>,
 
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        help = "skip paths matching PATTERN, in .slugifyignore syntax relative to the current directory, may be given more than once"
+    )]
+    ignore: Vec<String>,
+
+    #[arg(
+        long,
+        help = "also skip paths ignored by .gitignore, .ignore, .git/info/exclude and the global git excludes file"
+    )]
+    gitignore: bool,
+
     #[arg(
         long,
         help = "log to a filename in addition to stderr (or stdout if --log-to-stdout is passed in)"
@@ -57,6 +139,40 @@ pub struct SlugifyFilenames {
 
     #[arg(long, help = "log to stdout instead of stderr")]
     log_to_stdout: bool,
+
+    #[arg(
+        long,
+        help = "path to the journal recording every rename, defaults to .slugify-journal.jsonl in the current directory"
+    )]
+    journal: Option<Path>,
+
+    #[arg(long, conflicts_with_all = ["journal"], help = "do not write a journal")]
+    no_journal: bool,
+
+    #[arg(
+        long,
+        value_name = "JOURNAL",
+        conflicts_with_all = ["paths", "no_journal"],
+        help = "undo the renames recorded in the given journal in reverse order"
+    )]
+    undo: Option<Path>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = OutputFormat::Human,
+        help = "print a structured record for every entry to stdout instead of log lines"
+    )]
+    output: OutputFormat,
+
+    #[arg(
+        long,
+        help = "read settings from this file instead of slugify.toml and the user configuration file"
+    )]
+    config: Option<Path>,
+
+    #[arg(long, help = "print the settings in effect and exit")]
+    print_config: bool,
 }
 impl SlugifyFilenames {
     pub fn actual_verbosity(&self) -> Verbosity {
@@ -110,18 +226,23 @@ impl SlugifyFilenames {
         }
     }
     pub fn paths(&self) -> Vec<Path> {
+        self.paths_on(RealFilesystem)
+    }
+    /// `paths_on` is [`SlugifyFilenames::paths`] looking entries up on
+    /// `filesystem`
+    pub fn paths_on<F: Filesystem>(&self, filesystem: F) -> Vec<Path> {
         let paths = if self.paths.is_empty() {
             let cwd = Path::cwd().try_canonicalize();
             self.println(
                 format!("no paths provided, assuming {}", cwd.abbreviate()),
                 Verbosity::Debug,
             );
-            cwd.list().unwrap_or_default()
+            filesystem.list(&cwd).unwrap_or_default()
         } else {
             self.paths.clone()
         };
-        let all_paths_are_dirs = paths.iter().all(|path| path.try_canonicalize().is_dir());
-        if !self.recursive && all_paths_are_dirs {
+        let all_paths_are_dirs = paths.iter().all(|path| filesystem.is_dir(path));
+        if !self.is_recursive() && all_paths_are_dirs {
             self.eprintln(
                 "all target paths are directories but -r/--recursive was not provided",
                 Verbosity::Hint,
@@ -130,143 +251,570 @@ impl SlugifyFilenames {
         paths
             .into_iter()
             .filter(|path| {
-                if !path.exists() {
+                if !filesystem.exists(path) {
                     self.eprintln(format!("path does not exist: {path}"), Verbosity::Warning);
                 }
-                path.exists()
+                filesystem.exists(path)
             })
             .collect()
     }
     pub fn slugify_ignore_path(&self) -> Result<Path> {
-        if let Some(path) = &self.slugify_ignore {
-            if !path.exists() {
-                return Err(Error::IOError(format!(
-                    "the provided slugify ignore file does not exist: {path:#?}"
-                )));
-            }
+        self.slugify_ignore_path_on(RealFilesystem)
+    }
+    /// `slugify_ignore_path_on` is [`SlugifyFilenames::slugify_ignore_path`]
+    /// looking the file up on `filesystem`
+    pub fn slugify_ignore_path_on<F: Filesystem>(&self, filesystem: F) -> Result<Path> {
+        if let Some(path) = &self.slugify_ignore
+            && !filesystem.exists(path)
+        {
+            return Err(Error::not_found(path));
         }
         Ok(self
             .slugify_ignore
             .clone()
-            .unwrap_or_else(|| Path::new(".slugifyignore"))
+            .unwrap_or_else(|| Path::new(SLUGIFY_IGNORE_FILENAME))
             .try_canonicalize())
     }
-    pub fn slugify_ignore_lines(&self) -> Result<Vec<String>> {
-        let path = self.slugify_ignore_path()?;
-        if path.is_file() {
+    pub fn slugify_ignore_rules(&self) -> Result<IgnoreRules> {
+        self.slugify_ignore_rules_on(RealFilesystem)
+    }
+    /// `slugify_ignore_rules_on` is [`SlugifyFilenames::slugify_ignore_rules`]
+    /// reading the file from `filesystem`
+    pub fn slugify_ignore_rules_on<F: Filesystem>(&self, filesystem: F) -> Result<IgnoreRules> {
+        let path = self.slugify_ignore_path_on(&filesystem)?;
+        if filesystem.is_file(&path) {
             self.eprintln(format!("trying to read {path}"), Verbosity::Debug);
-            Ok(path
-                .read()?
-                .lines()
-                .map(|line| line.trim().to_string())
-                .filter(|line| line.len() > 0)
-                .collect::<Vec<String>>())
+            let contents = String::from_utf8_lossy(&filesystem.read(&path)?).to_string();
+            IgnoreRules::from_lines(&path.parent().unwrap_or_else(Path::cwd), contents.lines())
         } else {
-            Ok(Vec::new())
+            Ok(IgnoreRules::new(&Path::cwd()))
         }
     }
-    pub fn should_ignore(&self, lines: &Vec<String>, path: &Path) -> Result<bool> {
-        if lines.is_empty() {
-            Ok(false)
+    /// `ignores` are the rules of the .slugifyignore file followed by
+    /// the `--ignore` patterns, which take precedence over them
+    pub fn ignores(&self) -> Result<IgnoreStack> {
+        self.ignores_on(RealFilesystem)
+    }
+    /// `ignores_on` is [`SlugifyFilenames::ignores`] reading the
+    /// .slugifyignore file from `filesystem`
+    pub fn ignores_on<F: Filesystem>(&self, filesystem: F) -> Result<IgnoreStack> {
+        let mut ignores = IgnoreStack::from_rules(self.slugify_ignore_rules_on(filesystem)?);
+        ignores.push(IgnoreRules::from_lines(
+            &Path::cwd().try_canonicalize(),
+            &self.ignore,
+        )?);
+        Ok(ignores)
+    }
+    /// `should_ignore` is always false when `--force` is given except
+    /// for `.git` directories which are never touched
+    pub fn should_ignore(&self, ignores: &IgnoreStack, path: &Path) -> Result<bool> {
+        self.should_ignore_on(RealFilesystem, ignores, path)
+    }
+    /// `should_ignore_on` is [`SlugifyFilenames::should_ignore`] looking
+    /// `path` up on `filesystem`
+    pub fn should_ignore_on<F: Filesystem>(
+        &self,
+        filesystem: F,
+        ignores: &IgnoreStack,
+        path: &Path,
+    ) -> Result<bool> {
+        let path = filesystem
+            .canonicalize(path)
+            .unwrap_or_else(|_| path.try_absolute());
+        let is_dir = filesystem.is_dir(&path);
+        Ok(self
+            .planner_on(filesystem)?
+            .should_ignore(ignores, &path, is_dir))
+    }
+    /// `ignore_stack` returns the ignore rules in effect for the
+    /// top-level `path`, including version control ignore files when
+    /// `--gitignore` is given
+    pub fn ignore_stack(&self, ignores: &IgnoreStack, path: &Path) -> Result<IgnoreStack> {
+        if self.gitignore {
+            ignores.with_vcs_ignores(path)
         } else {
-            Ok(lines
-                .iter()
-                .map(|line| line.trim().to_string())
-                .filter(|line| line.len() > 0)
-                .any(|line| {
-                    let line = line.to_string();
-                    line == path.name()
-                        || line == path.relative_to_cwd().to_string()
-                        || line == path.to_string()
-                }))
+            Ok(ignores.clone())
         }
     }
-    pub fn unique_new_path(&self, path: &Path) -> Result<Path> {
-        let path = path.try_canonicalize();
-        let (name, extension) = if path.is_file() {
-            path.split_extension()
+    pub fn planner(&self) -> Result<Planner> {
+        self.planner_on(RealFilesystem)
+    }
+    /// `planner_on` is [`SlugifyFilenames::planner`] looking entries up
+    /// on `filesystem`
+    pub fn planner_on<F: Filesystem>(&self, filesystem: F) -> Result<Planner<F>> {
+        if !self.suffix_format.contains("{n}") {
+            return Err(Error::ConfigError(format!(
+                "--suffix-format {:#?} does not contain {{n}}",
+                self.suffix_format
+            )));
+        }
+        Ok(
+            Planner::with_filesystem(filesystem, self.parameters.slugifier()?)
+                .recursive(self.is_recursive())
+                .min_depth(self.min_depth())
+                .max_depth(self.max_depth())
+                .force(self.force)
+                .on_conflict(self.on_conflict())
+                .suffix_format(&self.suffix_format)
+                .suffix_start(self.suffix_start)
+                .exclude(&self.journal_path()),
+        )
+    }
+    /// `on_conflict` is `--on-conflict`, defaulting to
+    /// [`ConflictStrategy::Merge`] with `--force` and to
+    /// [`ConflictStrategy::Suffix`] otherwise
+    pub fn on_conflict(&self) -> ConflictStrategy {
+        match self.on_conflict {
+            Some(on_conflict) => on_conflict,
+            None if self.force => ConflictStrategy::Merge,
+            None => ConflictStrategy::Suffix,
+        }
+    }
+    pub fn journal_path(&self) -> Path {
+        self.journal
+            .clone()
+            .unwrap_or_else(|| Path::cwd().join(DEFAULT_JOURNAL_FILENAME))
+            .try_absolute()
+    }
+    /// `journal` is `None` with `--dry-run` or `--no-journal`
+    pub fn journal(&self) -> Option<Journal> {
+        self.journal_on(RealFilesystem)
+    }
+    /// `journal_on` is [`SlugifyFilenames::journal`] kept on
+    /// `filesystem`
+    pub fn journal_on<F: Filesystem>(&self, filesystem: F) -> Option<Journal<F>> {
+        if self.dry_run || self.no_journal {
+            None
         } else {
-            (path.name(), None)
-        };
-
-        let new_name = self.parameters.slugify_string(&name)?;
-        let new_extension = match extension.clone() {
-            Some(extension) => Some(self.parameters.slugify_string(extension)?),
-            None => None,
-        };
-
-        let mut count = 0;
-        let new_filename = Path::join_extension(&new_name, new_extension.clone());
-        let original_new_path = path.with_filename(&new_filename);
-        let mut new_path = original_new_path.clone();
-
-        if !new_path.exists() {
-            return Ok(new_path);
-        } else if path.name() == new_path.name().to_pascal_case() {
-            return Ok(new_path);
-        } else if path.name() == new_path.name().to_shouty_snake_case() {
-            return Ok(new_path);
-        } else if path.name() == new_path.name().to_shouty_kebab_case() {
-            return Ok(new_path);
-        }
-        while path.name() != new_path.name() && new_path.exists() {
-            let new_filename =
-                Path::join_extension(format!("{new_name}.{count}"), new_extension.clone());
-            new_path = path.with_filename(&new_filename);
-            count += 1;
+            Some(Journal::with_filesystem(filesystem, &self.journal_path()))
         }
-        log::info!(
-            "using new name {:#?} since {:#?} already exists",
-            new_path.to_string(),
-            original_new_path.to_string()
-        );
-        Ok(new_path)
     }
+    pub fn executor(&self) -> Executor {
+        self.executor_on(RealFilesystem)
+    }
+    /// `executor_on` is [`SlugifyFilenames::executor`] renaming entries
+    /// on `filesystem`
+    pub fn executor_on<F: Filesystem>(&self, filesystem: F) -> Executor<F> {
+        Executor::with_filesystem(filesystem)
+            .dry_run(self.dry_run)
+            .force(self.force)
+            .on_conflict(self.on_conflict())
+    }
+    /// `git_filesystem` renames tracked entries as `git mv` does with
+    /// `--git`, refusing modified ones unless `--force`
+    pub fn git_filesystem(&self) -> GitFilesystem {
+        GitFilesystem::new().force(self.force)
+    }
+    pub fn unique_new_path(&self, path: &Path) -> Result<Path> {
+        self.planner()?.unique_new_path(path)
+    }
+    /// `slugify_file_path` plans and performs the rename of `path`
+    /// alone, ignoring depth options
     pub fn slugify_file_path(&self, path: &Path) -> Result<Path> {
-        let path = path.canonicalize()?;
-        let new_path = self.unique_new_path(&path)?;
-        if path.to_string() != new_path.to_string() {
-            if self.dry_run {
+        let plan = self
+            .planner()?
+            .recursive(false)
+            .min_depth(0)
+            .plan(&[(path.clone(), IgnoreStack::new())])?;
+        let operation = &plan.operations()[0];
+        let outcome = self.executor().execute_operation(operation)?;
+        Ok(self
+            .report(&operation.from, &outcome)
+            .unwrap_or_else(|| operation.from.clone()))
+    }
+    /// `rename_path` performs a single rename, or only tells about it
+    /// with `--dry-run`
+    pub fn rename_path(&self, path: &Path, new_path: &Path) -> Result<Path> {
+        if path.to_string() == new_path.to_string() {
+            self.report_unchanged(path);
+            return Ok(path.clone());
+        }
+        let outcome = self.executor().rename(path, new_path)?;
+        Ok(self.report(path, &outcome).unwrap_or_else(|| path.clone()))
+    }
+    fn report_unchanged(&self, path: &Path) {
+        if self.debug {
+            self.println(format!("'{path}' == '{path}'"), Verbosity::Debug);
+        } else {
+            self.println(format!("unchanged: '{path}'"), Verbosity::Hint);
+        }
+    }
+    fn report(&self, path: &Path, outcome: &RenameOutcome) -> Option<Path> {
+        match outcome {
+            RenameOutcome::Renamed(new_path) => {
+                self.println(format!("{path} -> {new_path}"), Verbosity::Info);
+                Some(new_path.clone())
+            }
+            RenameOutcome::WouldRename(new_path) => {
                 self.println(
                     format!("would rename {path} to {new_path}"),
                     Verbosity::Info,
                 );
-                return Ok(new_path);
+                Some(new_path.clone())
             }
-            if path.is_dir() && new_path.is_dir() && self.force {
-                if self.debug {
-                    dbg!(path.is_dir(), new_path.is_dir(), self.force);
-                }
-                return Ok(new_path.try_canonicalize());
-            } else if path.exists() && new_path.exists() && !self.force {
-                return Err(Error::IOError(format!(
-                    "{new_path} already exists, use --force to overwrite"
-                )));
+            RenameOutcome::Unchanged => {
+                self.report_unchanged(path);
+                None
             }
-            let new_path = match path.rename(&new_path, true) {
-                Ok(new_path) => new_path,
-                Err(error) => return Err(Error::IOError(format!("{}", error))),
-            };
-            self.println(format!("{path} -> {new_path}"), Verbosity::Info);
-            Ok(new_path.try_canonicalize())
+        }
+    }
+    pub fn slugify_path(&self, path: &Path, ignores: &IgnoreStack) -> Result<()> {
+        self.slugify_paths(&[(path.clone(), ignores.clone())])
+    }
+    /// `slugify_paths` plans and performs the renames of `targets`,
+    /// carrying on past the entries that fail with `--keep-going`
+    pub fn slugify_paths(&self, targets: &[(Path, IgnoreStack)]) -> Result<()> {
+        if self.git {
+            self.slugify_paths_on(self.git_filesystem(), targets)
         } else {
-            if self.debug {
-                self.println(format!("'{path}' == '{new_path}'"), Verbosity::Debug);
+            self.slugify_paths_on(RealFilesystem, targets)
+        }
+    }
+    /// `slugify_paths_on` is [`SlugifyFilenames::slugify_paths`]
+    /// looking entries up and renaming them on `filesystem`
+    pub fn slugify_paths_on<F: Filesystem>(
+        &self,
+        filesystem: F,
+        targets: &[(Path, IgnoreStack)],
+    ) -> Result<()> {
+        let planner = self.planner_on(&filesystem)?;
+        let (plan, failures) = if self.keep_going {
+            planner.plan_keep_going(targets)?
+        } else {
+            (planner.plan(targets)?, Vec::new())
+        };
+        if self.check {
+            self.check_plan(&plan, failures)
+        } else {
+            self.execute_plan_on(&self.executor_on(&filesystem), &plan, failures)
+        }
+    }
+    /// `check_plan` prints every entry that `plan` would rename along
+    /// with its suggested name to stdout, failing with
+    /// [`Error::CheckFailed`] when there is any. Entries that could not
+    /// be planned with `--keep-going` take precedence: they are
+    /// summarized along with the count of entries that would be
+    /// renamed and the check fails with [`Error::PartialFailure`].
+    pub fn check_plan(&self, plan: &RenamePlan, failures: Vec<(Path, Error)>) -> Result<()> {
+        let mut records = Vec::<RenameRecord>::new();
+        for (path, error) in &failures {
+            self.record(RenameRecord::error(path, path, None, error), &mut records)?;
+        }
+        let mut count = 0;
+        for operation in plan.renames() {
+            count += 1;
+            if self.output.is_structured() {
+                let outcome = RenameOutcome::WouldRename(operation.to.clone());
+                self.record(RenameRecord::new(operation, &outcome), &mut records)?;
             } else {
-                self.println(format!("unchanged: '{path}'"), Verbosity::Hint);
+                println!("{} -> {}", operation.original, operation.to);
             }
-            Ok(path.try_canonicalize())
         }
+        self.flush_records(&records)?;
+        if !failures.is_empty() {
+            self.print_failures(&failures);
+            self.eprintln(Error::CheckFailed { count }, Verbosity::Warning);
+            return Err(Error::PartialFailure {
+                failed: failures.len(),
+            });
+        }
+        if count > 0 {
+            return Err(Error::CheckFailed { count });
+        }
+        Ok(())
+    }
+    /// `plan` walks the given paths without touching the filesystem,
+    /// see [`Planner::plan`]
+    pub fn plan(&self, targets: &[(Path, IgnoreStack)]) -> Result<RenamePlan> {
+        self.planner()?.plan(targets)
+    }
+    /// `execute_plan` performs every operation of `plan` in order
+    pub fn execute_plan(&self, plan: &RenamePlan) -> Result<()> {
+        self.execute_plan_with_failures(plan, Vec::new())
     }
-    pub fn slugify_path(&self, path: &Path) -> Result<()> {
-        let new_path = self.slugify_file_path(path)?;
-        if self.recursive && new_path.is_dir() {
-            for sub_path in new_path.list()? {
-                self.slugify_path(&sub_path)?;
+    /// `execute_plan_with_failures` is [`SlugifyFilenames::execute_plan`]
+    /// for a plan whose `failures` could not be planned. With
+    /// `--keep-going` the operations that fail are added to them, the
+    /// entries below a directory that could not be renamed are renamed
+    /// where they are, and every failure is summarized at the end.
+    pub fn execute_plan_with_failures(
+        &self,
+        plan: &RenamePlan,
+        failures: Vec<(Path, Error)>,
+    ) -> Result<()> {
+        if self.git {
+            self.execute_plan_on(&self.executor_on(self.git_filesystem()), plan, failures)
+        } else {
+            self.execute_plan_on(&self.executor(), plan, failures)
+        }
+    }
+    /// `execute_plan_on` is [`SlugifyFilenames::execute_plan_with_failures`]
+    /// renaming entries and rewriting references with `executor`
+    pub fn execute_plan_on<F: Filesystem>(
+        &self,
+        executor: &Executor<F>,
+        plan: &RenamePlan,
+        mut failures: Vec<(Path, Error)>,
+    ) -> Result<()> {
+        let journal = self.journal_on(executor.filesystem());
+        let mut references = ReferenceRewriter::new(&self.rewrite_references);
+        let mut records = Vec::<RenameRecord>::new();
+        for (path, error) in &failures {
+            self.record(RenameRecord::error(path, path, None, error), &mut records)?;
+        }
+        // directories whose rename failed as (to, from)
+        let mut failed_directories = Vec::<(Path, Path)>::new();
+        // where the given paths are once renamed
+        let mut targets = Vec::<Path>::new();
+        for operation in plan {
+            let operation = &relocate(operation, &failed_directories);
+            match operation.reason {
+                RenameReason::Ignored => {
+                    self.println(format!("ignored: '{}'", operation.from), Verbosity::Debug);
+                    self.record(
+                        RenameRecord::new(operation, &RenameOutcome::Unchanged),
+                        &mut records,
+                    )?;
+                    continue;
+                }
+                RenameReason::AboveMinDepth => {
+                    self.println(
+                        format!("skipping '{}' above --min-depth", operation.from),
+                        Verbosity::Debug,
+                    );
+                }
+                _ => {}
+            }
+            let outcome = match executor.execute_operation(operation) {
+                Ok(outcome) => outcome,
+                Err(error) => {
+                    self.record(
+                        RenameRecord::error(
+                            &operation.from,
+                            &operation.to,
+                            Some(operation.reason),
+                            &error,
+                        ),
+                        &mut records,
+                    )?;
+                    if self.keep_going {
+                        self.eprintln(&error, Verbosity::Warning);
+                        if operation.is_dir {
+                            failed_directories.push((operation.to.clone(), operation.from.clone()));
+                        }
+                        if operation.depth == 0 {
+                            targets.push(operation.from.clone());
+                        }
+                        failures.push((operation.from.clone(), error));
+                        continue;
+                    }
+                    self.flush_records(&records)?;
+                    if operation.depth == 0 {
+                        targets.push(operation.from.clone());
+                    }
+                    self.rewrite_references(executor.filesystem(), &references, &targets)?;
+                    if let Error::TargetExists { .. } = error {
+                        self.eprintln(
+                            "use --on-conflict overwrite or --force to overwrite",
+                            Verbosity::Hint,
+                        );
+                    }
+                    return Err(error);
+                }
+            };
+            // merged directories cannot be split back apart by --undo
+            if let (Some(journal), RenameOutcome::Renamed(new_path)) = (&journal, &outcome)
+                && operation.reason != RenameReason::MergedIntoExistingDirectory
+            {
+                journal.append(&JournalEntry::new(
+                    executor.filesystem(),
+                    &operation.from,
+                    new_path,
+                ))?;
+            }
+            if let RenameOutcome::Renamed(new_path) | RenameOutcome::WouldRename(new_path) =
+                &outcome
+            {
+                references.rename(&operation.original, new_path);
+            }
+            if operation.depth == 0 {
+                targets.push(match &outcome {
+                    RenameOutcome::Renamed(new_path) => new_path.clone(),
+                    _ => operation.from.clone(),
+                });
+            }
+            if !self.output.is_structured() {
+                self.report(&operation.from, &outcome);
             }
+            self.record(RenameRecord::new(operation, &outcome), &mut records)?;
+        }
+        self.flush_records(&records)?;
+        self.rewrite_references(executor.filesystem(), &references, &targets)?;
+        if !failures.is_empty() {
+            self.print_failures(&failures);
+            return Err(Error::PartialFailure {
+                failed: failures.len(),
+            });
         }
         Ok(())
     }
+    /// `rewrite_references` rewrites the references registered in
+    /// `references` inside the files below `targets`, or below their
+    /// git working trees with `--git`, which match
+    /// `--rewrite-references` relative to the directory containing
+    /// them, skipping the entries the planner ignores. The diffs are
+    /// only printed with `--dry-run`.
+    pub fn rewrite_references<F: Filesystem>(
+        &self,
+        filesystem: &F,
+        references: &ReferenceRewriter,
+        targets: &[Path],
+    ) -> Result<()> {
+        if references.is_empty() {
+            return Ok(());
+        }
+        let mut roots = BTreeMap::<String, (Path, Path)>::new();
+        for target in targets {
+            let (base, root) = match git_work_tree(target).filter(|_| self.git) {
+                Some(work_tree) => (work_tree.clone(), work_tree),
+                None => (target.parent().unwrap_or_else(Path::cwd), target.clone()),
+            };
+            roots.insert(root.to_string(), (base, root));
+        }
+        let planner = self.planner_on(filesystem)?;
+        let ignores = self.ignores_on(filesystem)?;
+        let mut files = BTreeSet::<String>::new();
+        for (base, root) in roots.values() {
+            let ignores = self.ignore_stack(&ignores, root)?;
+            files.extend(
+                references
+                    .files(filesystem, base, root, &ignores, |ignores, path, is_dir| {
+                        planner.should_ignore(ignores, path, is_dir)
+                    })?
+                    .iter()
+                    .map(Path::to_string),
+            );
+        }
+        for file in files.iter().map(Path::new) {
+            let Some(diff) = references.rewrite_file(filesystem, &file, self.dry_run)? else {
+                continue;
+            };
+            if !self.dry_run {
+                self.println(format!("rewrote references in {file}"), Verbosity::Info);
+            } else if self.output.is_structured() {
+                self.println(diff.trim_end(), Verbosity::Info);
+            } else {
+                print!("{diff}");
+            }
+        }
+        Ok(())
+    }
+    /// `print_failures` prints a table of the entries that could not
+    /// be renamed
+    fn print_failures(&self, failures: &[(Path, Error)]) {
+        let width = failures
+            .iter()
+            .map(|(path, _)| path.to_string().chars().count())
+            .chain(["PATH".len()])
+            .max()
+            .unwrap_or_default();
+        self.eprintln(
+            format!("{} entries could not be renamed:", failures.len()),
+            Verbosity::Warning,
+        );
+        self.eprintln(format!("{:<width$}  ERROR", "PATH"), Verbosity::Warning);
+        for (path, error) in failures {
+            self.eprintln(
+                format!("{:<width$}  {error}", path.to_string()),
+                Verbosity::Warning,
+            );
+        }
+    }
+    /// `record` prints `record` right away with `--output jsonl` or
+    /// keeps it for [`SlugifyFilenames::flush_records`] with `--output json`
+    fn record(&self, record: RenameRecord, records: &mut Vec<RenameRecord>) -> Result<()> {
+        match self.output {
+            OutputFormat::Human => {}
+            OutputFormat::Jsonl => println!("{}", record.to_json()?),
+            OutputFormat::Json => records.push(record),
+        }
+        Ok(())
+    }
+    fn flush_records(&self, records: &[RenameRecord]) -> Result<()> {
+        if self.output == OutputFormat::Json {
+            println!("{}", serde_json::to_string_pretty(records)?);
+        }
+        Ok(())
+    }
+    /// `undo_journal` renames the entries of `journal` back, see [`Journal::undo`]
+    pub fn undo_journal(&self, journal: &Path) -> Result<()> {
+        let journal = journal.try_absolute();
+        if self.git {
+            self.undo_journal_on(&self.executor_on(self.git_filesystem()), &journal)
+        } else {
+            self.undo_journal_on(&self.executor(), &journal)
+        }
+    }
+    /// `undo_journal_on` is [`SlugifyFilenames::undo_journal`] renaming
+    /// entries back with `executor`
+    pub fn undo_journal_on<F: Filesystem>(
+        &self,
+        executor: &Executor<F>,
+        journal: &Path,
+    ) -> Result<()> {
+        let journal = Journal::with_filesystem(executor.filesystem(), journal);
+        if !journal.exists() {
+            return Err(Error::not_found(journal.path()));
+        }
+        let mut records = Vec::<RenameRecord>::new();
+        let mut refused = 0;
+        let results = journal.undo(executor)?;
+        for (entry, result) in results {
+            let operation = entry.undo_operation();
+            let record = match result {
+                Ok(outcome) => {
+                    if !self.output.is_structured() {
+                        self.report(&entry.to, &outcome);
+                    }
+                    RenameRecord::new(&operation, &outcome)
+                }
+                Err(error) => {
+                    refused += 1;
+                    self.eprintln(&error, Verbosity::Warning);
+                    RenameRecord::error(&entry.to, &entry.from, None, &error)
+                }
+            };
+            self.record(record, &mut records)?;
+        }
+        self.flush_records(&records)?;
+        if refused > 0 {
+            return Err(Error::UndoIncomplete {
+                refused,
+                journal: journal.path().clone(),
+            });
+        }
+        Ok(())
+    }
+    /// `is_recursive` is true when either -r/--recursive,
+    /// --max-depth or --min-depth are given
+    pub fn is_recursive(&self) -> bool {
+        self.recursive || self.max_depth.is_some() || self.min_depth.is_some()
+    }
+    pub fn min_depth(&self) -> usize {
+        self.min_depth.unwrap_or_default()
+    }
+    pub fn max_depth(&self) -> Option<usize> {
+        self.max_depth
+    }
+    /// `should_descend` tells whether the children of an entry at
+    /// `depth` are within reach
+    pub fn should_descend(&self, depth: usize) -> bool {
+        self.is_recursive()
+            && match self.max_depth {
+                Some(max_depth) => depth < max_depth,
+                None => true,
+            }
+    }
     fn initialize(&mut self) -> std::result::Result<(), fern::InitError> {
         let verbosity_level = self.verbose;
         let mut chain = fern::Dispatch::new().format(move |out, message, record| {
@@ -299,50 +847,303 @@ impl SlugifyFilenames {
     }
     pub fn execute(args: Vec<String>) -> Result<()> {
         // export RUST_LOG=debug
-        let mut cli = SlugifyFilenames::parse_from(args);
+        let (mut cli, config) = crate::cli::parse_configured::<SlugifyFilenames>(args.clone())?;
+        if cli.print_config {
+            return crate::cli::print_config::<SlugifyFilenames>(&config, &args);
+        }
         cli.initialize()?;
+        for file in config.files() {
+            cli.println(format!("read settings from {file}"), Verbosity::Debug);
+        }
 
-        let ignores = cli.slugify_ignore_lines()?;
-        let paths = cli.paths();
+        if cli.git {
+            cli.run_on(cli.git_filesystem())
+        } else {
+            cli.run_on(RealFilesystem)
+        }
+    }
+    /// `run_on` undoes the journal given with `--undo` or slugifies the
+    /// given paths, looking entries up and renaming them on `filesystem`
+    pub fn run_on<F: Filesystem>(&self, filesystem: F) -> Result<()> {
+        if let Some(journal) = &self.undo {
+            return self.undo_journal_on(&self.executor_on(filesystem), &journal.try_absolute());
+        }
+
+        let ignores = self.ignores_on(&filesystem)?;
+        let paths = self.paths_on(&filesystem);
 
         let total_paths = paths.len();
+        if self.git && !paths.iter().any(|path| git_work_tree(path).is_some()) {
+            self.eprintln(
+                "--git was given but none of the paths is inside a git working tree",
+                Verbosity::Hint,
+            );
+        }
 
-        if cli.debug {
+        if self.debug {
             dbg!(&ignores);
         }
-        let (target_paths, total_filtered) = if !cli.force && ignores.len() > 0 {
-            let filtered_paths = paths
-                .clone()
-                .into_iter()
-                .filter(|old_path| cli.should_ignore(&ignores, &old_path).unwrap_or_default())
-                .collect::<Vec<Path>>();
-            let count = filtered_paths.len();
-            (filtered_paths.clone(), Some(count))
-        } else {
-            (paths.clone(), None)
-        };
-        if cli.debug {
-            dbg!(&target_paths, &total_filtered);
+        let mut target_paths = Vec::<(Path, IgnoreStack)>::new();
+        for path in paths.iter() {
+            let ignores = self.ignore_stack(&ignores, path)?;
+            if !self.should_ignore_on(&filesystem, &ignores, path)? {
+                target_paths.push((path.clone(), ignores));
+            }
+        }
+        if self.debug {
+            dbg!(&target_paths);
         }
 
-        if total_filtered.is_some() && total_filtered.clone().unwrap() == 0 {
+        if target_paths.is_empty() {
             if total_paths > 0 {
-                cli.println(
+                self.println(
                     format!("total paths is {total_paths} but all have been ignored: "),
                     Verbosity::Warning,
                 );
                 for path in paths.iter() {
                     let path = path.relative_to_cwd();
-                    cli.println(format!("    {path}"), Verbosity::Warning);
+                    self.println(format!("    {path}"), Verbosity::Warning);
                 }
             } else {
-                cli.println(format!("no paths to slugify"), Verbosity::Warning);
+                self.println("no paths to slugify", Verbosity::Warning);
             }
             return Ok(());
         }
-        for old_path in target_paths {
-            cli.slugify_path(&old_path)?;
+        self.slugify_paths_on(filesystem, &target_paths)
+    }
+}
+
+/// `relocate` points `operation` back below the original location of
+/// the closest of `failed_directories`, given as (to, from), that it
+/// was planned to be moved along with
+fn relocate(operation: &RenameOperation, failed_directories: &[(Path, Path)]) -> RenameOperation {
+    let mut operation = operation.clone();
+    for (to, from) in failed_directories.iter().rev() {
+        let prefix = format!("{}/", to.to_string().trim_end_matches('/'));
+        if let Some(below) = operation.from.to_string().strip_prefix(&prefix) {
+            let target = operation.to.to_string();
+            let target = target.strip_prefix(&prefix).unwrap_or(&target);
+            operation.from = from.join(below);
+            operation.to = from.join(target);
+            break;
         }
+    }
+    operation
+}
+
+#[cfg(test)]
+mod slugify_filenames_tests {
+    use crate::{Error, Filesystem, IgnoreStack, MemoryFilesystem, Result, SlugifyFilenames};
+    use clap::Parser;
+    use iocore::Path;
+
+    fn cli(args: &[&str]) -> SlugifyFilenames {
+        SlugifyFilenames::parse_from(["slugify-filenames"].iter().chain(args.iter()))
+    }
+
+    #[test]
+    fn test_depth_options_imply_recursion() {
+        assert!(!cli(&[]).is_recursive());
+        assert!(cli(&["-r"]).is_recursive());
+        assert!(cli(&["--max-depth", "1"]).is_recursive());
+        assert!(cli(&["--min-depth", "1"]).is_recursive());
+    }
+
+    #[test]
+    fn test_should_descend_honors_max_depth() {
+        assert!(!cli(&[]).should_descend(0));
+        assert!(cli(&["-r"]).should_descend(100));
+        let two_levels = cli(&["--max-depth", "2"]);
+        assert!(two_levels.should_descend(0));
+        assert!(two_levels.should_descend(1));
+        assert!(!two_levels.should_descend(2));
+        assert!(!cli(&["--max-depth", "0"]).should_descend(0));
+    }
+
+    #[test]
+    fn test_dry_run_plans_children_of_renamed_directories() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem = MemoryFilesystem::new().with_files(
+            &root,
+            ["Top Dir/Sub Dir/C D.txt", "Top Dir/A B", "Top Dir/A-B"],
+        );
+
+        let cli = cli(&["-r", "--dry-run"]);
+        let plan = cli
+            .planner_on(&filesystem)?
+            .plan(&[(root.join("Top Dir"), IgnoreStack::new())])?;
+        let plan = plan
+            .iter()
+            .map(|operation| {
+                (
+                    operation.from.relative_to(&root).to_string(),
+                    operation.to.relative_to(&root).to_string(),
+                )
+            })
+            .collect::<Vec<(String, String)>>();
+        assert_eq!(
+            plan,
+            vec![
+                ("Top Dir".to_string(), "Top-Dir".to_string()),
+                ("Top-Dir/A B".to_string(), "Top-Dir/A-B.0".to_string()),
+                ("Top-Dir/A-B".to_string(), "Top-Dir/A-B".to_string()),
+                ("Top-Dir/Sub Dir".to_string(), "Top-Dir/Sub-Dir".to_string()),
+                (
+                    "Top-Dir/Sub-Dir/C D.txt".to_string(),
+                    "Top-Dir/Sub-Dir/C-D.txt".to_string()
+                ),
+            ]
+        );
+        assert!(filesystem.exists(&root.join("Top Dir/Sub Dir/C D.txt")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_on_conflict_merge_moves_contents_into_existing_directory() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem = MemoryFilesystem::new()
+            .with_files(&root, ["Some Dir/Sub/A File.txt", "Some-Dir/Sub/b.txt"]);
+
+        let cli = cli(&["-r", "-q", "--no-journal", "--on-conflict", "merge"]);
+        let plan = cli
+            .planner_on(&filesystem)?
+            .plan(&[(root.join("Some Dir"), IgnoreStack::new())])?;
+        cli.execute_plan_on(&cli.executor_on(&filesystem), &plan, Vec::new())?;
+        assert!(!filesystem.exists(&root.join("Some Dir")));
+        assert!(filesystem.exists(&root.join("Some-Dir/Sub/A-File.txt")));
+        assert!(filesystem.exists(&root.join("Some-Dir/Sub/b.txt")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_restores_journaled_renames() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem = MemoryFilesystem::new().with_files(&root, ["Top Dir/A B.txt"]);
+        let journal = root.join("journal.jsonl");
+        let journal_arg = journal.to_string();
+
+        let cli = cli(&["-r", "-q", "--journal", &journal_arg]);
+        let executor = cli.executor_on(&filesystem);
+        let plan = cli
+            .planner_on(&filesystem)?
+            .plan(&[(root.clone(), IgnoreStack::new())])?;
+        cli.execute_plan_on(&executor, &plan, Vec::new())?;
+        assert!(filesystem.exists(&root.join("Top-Dir/A-B.txt")));
+        assert!(filesystem.is_file(&journal));
+
+        self::cli(&["-q", "--undo", &journal_arg]).undo_journal_on(&executor, &journal)?;
+        assert!(filesystem.exists(&root.join("Top Dir/A B.txt")));
+        assert!(!filesystem.exists(&journal));
+        Ok(())
+    }
+
+    #[test]
+    fn test_keep_going_renames_past_failures() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem = MemoryFilesystem::new().with_files(&root, ["Top Dir/A B.txt", "C D.txt"]);
+
+        let cli = cli(&["-r", "-q", "--no-journal", "--keep-going"]);
+        let plan = cli
+            .planner_on(&filesystem)?
+            .plan(&[(root.clone(), IgnoreStack::new())])?;
+        // taken after planning, so renaming "Top Dir" fails
+        filesystem.write(&root.join("Top-Dir/taken.txt"), &[]);
+        let error = cli
+            .execute_plan_on(&cli.executor_on(&filesystem), &plan, Vec::new())
+            .unwrap_err();
+        assert!(matches!(error, Error::PartialFailure { failed: 1 }));
+        assert_eq!(error.exit_code(), 4);
+        assert!(filesystem.exists(&root.join("Top Dir/A-B.txt")));
+        assert!(filesystem.exists(&root.join("C-D.txt")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_arguments_are_usage_errors() {
+        let error = SlugifyFilenames::execute(vec![
+            "slugify-filenames".to_string(),
+            "--no-such-flag".to_string(),
+        ])
+        .unwrap_err();
+        assert!(matches!(error, Error::UsageError(_)));
+        assert_eq!(error.exit_code(), crate::EXIT_USAGE);
+        assert!(error.to_string().contains("--no-such-flag"));
+    }
+
+    #[test]
+    fn test_check_leaves_entries_untouched() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem = MemoryFilesystem::new().with_files(&root, ["Top Dir/A B.txt"]);
+        let entries = filesystem.paths(&root);
+
+        let cli = cli(&["-r", "-q", "--check"]);
+        let check = |path: &str| {
+            let plan = cli
+                .planner_on(&filesystem)?
+                .plan(&[(root.join(path), IgnoreStack::new())])?;
+            cli.check_plan(&plan, Vec::new())
+        };
+        let error = check("Top Dir").unwrap_err();
+        assert!(matches!(error, Error::CheckFailed { count: 2 }));
+        assert_eq!(error.exit_code(), crate::EXIT_CHECK_FAILED);
+        assert_eq!(filesystem.paths(&root), entries);
+
+        filesystem.create_dir(&root.join("top-dir"));
+        check("top-dir")?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_with_keep_going_fails_with_the_planning_failures() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem =
+            MemoryFilesystem::new().with_files(&root, ["A B.txt", "A-B.txt", "C D.txt"]);
+        let entries = filesystem.paths(&root);
+
+        let cli = cli(&[
+            "-r",
+            "-q",
+            "--check",
+            "--keep-going",
+            "--on-conflict",
+            "error",
+        ]);
+        let (plan, failures) = cli
+            .planner_on(&filesystem)?
+            .plan_keep_going(&[(root.clone(), IgnoreStack::new())])?;
+        assert_eq!(plan.renames().count(), 1);
+        let error = cli.check_plan(&plan, failures).unwrap_err();
+        assert!(matches!(error, Error::PartialFailure { failed: 1 }));
+        assert_eq!(error.exit_code(), crate::EXIT_PARTIAL_FAILURE);
+        assert_eq!(filesystem.paths(&root), entries);
+        Ok(())
+    }
+
+    #[test]
+    fn test_rewrite_references_to_renamed_entries() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem = MemoryFilesystem::new().with_files(&root, ["Images/My Image.png"]);
+        filesystem.write(&root.join("index.md"), b"![](Images/My%20Image.png)\n");
+        // outside of the given paths
+        filesystem.write(
+            &Path::new("/elsewhere/index.md"),
+            b"![](/virtual/Images/My%20Image.png)\n",
+        );
+
+        let cli = cli(&["-r", "-q", "--no-journal", "--rewrite-references", "*.md"]);
+        let plan = cli
+            .planner_on(&filesystem)?
+            .plan(&[(root.clone(), IgnoreStack::new())])?;
+        cli.execute_plan_on(&cli.executor_on(&filesystem), &plan, Vec::new())?;
+        assert!(filesystem.exists(&root.join("Images/My-Image.png")));
+        assert_eq!(
+            filesystem.read(&root.join("index.md"))?,
+            b"![](Images/My-Image.png)\n"
+        );
+        assert_eq!(
+            filesystem.read(&Path::new("/elsewhere/index.md"))?,
+            b"![](/virtual/Images/My%20Image.png)\n"
+        );
         Ok(())
     }
 }
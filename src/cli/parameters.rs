@@ -1,38 +1,154 @@
 use crate::errors::*;
+use crate::slugifier::{
+    Case, DEFAULT_ALLOWED_CHARS, DEFAULT_COMPOUND_EXTENSIONS, ExtensionHandling, SlugOptions,
+    Slugifier,
+};
 use crate::string::*;
 use clap::Args;
 
 #[derive(Args, Debug, Clone)]
 #[group()]
 pub struct SlugifyParameters {
-    #[arg(short, long, default_value = "-")]
-    separator: Option<char>,
+    #[arg(
+        short,
+        long,
+        help = "slug style bundling separator, case, allowed characters, length limit and extension handling: web, python, posix-portable, windows-safe, s3-key, ascii-strict or a profile of the configuration files, overridden by the other flags"
+    )]
+    preset: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        default_value = "-",
+        help = "separator used in place of whitespace and special characters, may be more than one character long"
+    )]
+    separator: Option<String>,
 
     #[arg(
         short,
         long,
-        help = "lowercase slugified filenames. The default is to not change the case so that, for example, a file named \"README.md\" does not become \"readme.md\""
+        help = "lowercase slugified filenames, same as --case lower. The default is to not change the case so that, for example, a file named \"README.md\" does not become \"readme.md\""
     )]
     lowercase: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        conflicts_with = "lowercase",
+        help = "case style applied after transliteration, the word styles (kebab to shouty-kebab) ignore --separator [default: preserve]"
+    )]
+    case: Option<Case>,
+
+    #[arg(
+        long,
+        default_value = DEFAULT_ALLOWED_CHARS,
+        help = "characters kept verbatim, expressed as the body of a regex character class"
+    )]
+    allowed_chars: String,
+
+    #[arg(
+        long,
+        help = "truncate slugs to at most this many bytes, file extensions included, only the stem is shortened"
+    )]
+    max_length: Option<usize>,
+
+    #[arg(
+        long,
+        help = "do not transliterate non-ascii characters before slugifying"
+    )]
+    no_transliterate: bool,
+
+    #[arg(long, value_enum, default_value_t = ExtensionHandling::Slugify, help = "how to treat file extensions")]
+    extension: ExtensionHandling,
+
+    #[arg(
+        long,
+        value_name = "EXTENSION",
+        help = "multi-part extension kept together with the default ones (e.g.: tar.gz, d.ts, min.js), may be given more than once"
+    )]
+    compound_extension: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "FROM=TO",
+        value_parser = parse_replacement,
+        help = "replace every occurrence of FROM with TO before slugifying, e.g.: \"&= and \", may be given more than once"
+    )]
+    replace: Vec<String>,
+
+    #[arg(
+        long,
+        help = "append the separator to names Windows reserves for devices (CON, PRN, AUX, NUL, COM1-COM9 and LPT1-LPT9, with or without an extension) and strip trailing dots and spaces"
+    )]
+    avoid_reserved_names: bool,
 }
 
 impl SlugifyParameters {
     pub fn slugify_string<T: std::string::ToString>(&self, string: T) -> Result<String> {
-        Ok(crate::string::slugify_string(string, self.lowercase)?)
+        self.slugifier()?.slugify(string)
     }
-    pub fn separator(&self) -> Option<char> {
-        match self.separator {
-            Some(separator) => Some(separator),
-            None => None,
-        }
+    pub fn slug_options(&self) -> SlugOptions {
+        let options = SlugOptions::new()
+            .separator(self.non_option_separator())
+            .case(self.case())
+            .allowed_chars(&self.allowed_chars)
+            .max_length(self.max_length)
+            .transliterate(!self.no_transliterate)
+            .extension(self.extension)
+            .compound_extensions(self.compound_extensions())
+            .avoid_reserved_names(self.avoid_reserved_names);
+        self.replacements()
+            .into_iter()
+            .fold(options, |options, (from, to)| options.replace(from, to))
+    }
+    /// `replacements` are the `--replace` rules split into (from, to)
+    pub fn replacements(&self) -> Vec<(String, String)> {
+        self.replace
+            .iter()
+            .filter_map(|replacement| replacement.split_once('='))
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .collect()
+    }
+    /// `compound_extensions` are [`DEFAULT_COMPOUND_EXTENSIONS`]
+    /// followed by every `--compound-extension`
+    pub fn compound_extensions(&self) -> Vec<String> {
+        DEFAULT_COMPOUND_EXTENSIONS
+            .iter()
+            .map(|extension| extension.to_string())
+            .chain(self.compound_extension.iter().cloned())
+            .collect()
+    }
+    pub fn slugifier(&self) -> Result<Slugifier> {
+        self.slug_options().build()
+    }
+    pub fn separator(&self) -> Option<String> {
+        self.separator.clone()
     }
     pub fn lowercase(&self) -> bool {
         self.lowercase
     }
-    pub fn non_option_separator(&self) -> char {
-        match self.separator {
-            Some(separator) => separator,
-            None => DEFAULT_SEPARATOR,
+    /// `case` is [`Case::Lower`] with `-l/--lowercase`, otherwise
+    /// `--case` defaulting to [`Case::Preserve`]
+    pub fn case(&self) -> Case {
+        if self.lowercase {
+            Case::Lower
+        } else {
+            self.case.unwrap_or_default()
+        }
+    }
+    pub fn non_option_separator(&self) -> String {
+        match &self.separator {
+            Some(separator) => separator.to_string(),
+            None => DEFAULT_SEPARATOR.to_string(),
         }
     }
 }
+
+/// `parse_replacement` accepts `--replace` values made of a non-empty
+/// FROM and a possibly empty TO separated by `=`
+fn parse_replacement(value: &str) -> std::result::Result<String, String> {
+    match value.split_once('=') {
+        Some((from, _)) if !from.is_empty() => Ok(value.to_string()),
+        _ => Err(format!("expected FROM=TO, got {value:#?}")),
+    }
+}
@@ -1,6 +1,9 @@
 use crate::cli::parameters::SlugifyParameters;
 use crate::errors::*;
+use crate::slugifier::Slugifier;
 use clap::Parser;
+use iocore::Path;
+use std::io::{BufRead, Write};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -10,18 +13,123 @@ use clap::Parser;
     long_about = "slugify-string command-line utility"
 )]
 pub struct SlugifyString {
-    #[arg()]
+    #[arg(help = "text to slugify, read from stdin when omitted or \"-\"")]
     text: Vec<String>,
 
     #[command(flatten)]
     parameters: SlugifyParameters,
+
+    #[arg(
+        short = 'L',
+        long,
+        help = "slugify each line of stdin independently instead of all of it at once"
+    )]
+    lines: bool,
+
+    #[arg(
+        short = '0',
+        long,
+        conflicts_with = "lines",
+        help = "read NUL-delimited text from stdin and write NUL-delimited slugs"
+    )]
+    null: bool,
+
+    #[arg(
+        long,
+        help = "read settings from this file instead of slugify.toml and the user configuration file"
+    )]
+    config: Option<Path>,
+
+    #[arg(long, help = "print the settings in effect and exit")]
+    print_config: bool,
 }
 impl SlugifyString {
+    /// `reads_stdin` is true when no text or only "-" is given
+    pub fn reads_stdin(&self) -> bool {
+        self.text.is_empty() || self.text == ["-"]
+    }
+    /// `slugify_input` slugifies `input` into `output` according to
+    /// `--lines` and `--null`
+    pub fn slugify_input(
+        &self,
+        slugifier: &Slugifier,
+        mut input: impl BufRead,
+        mut output: impl Write,
+    ) -> Result<()> {
+        if self.null || self.lines {
+            let delimiter = if self.null { b'\0' } else { b'\n' };
+            for record in input.split(delimiter) {
+                let record = record?;
+                let text = String::from_utf8_lossy(&record);
+                let text = if self.lines {
+                    text.trim_end_matches('\r')
+                } else {
+                    &text
+                };
+                output.write_all(slugifier.slugify(text)?.as_bytes())?;
+                output.write_all(&[delimiter])?;
+            }
+        } else {
+            let mut text = Vec::new();
+            input.read_to_end(&mut text)?;
+            let text = String::from_utf8_lossy(&text);
+            writeln!(output, "{}", slugifier.slugify(text.trim())?)?;
+        }
+        output.flush()?;
+        Ok(())
+    }
     pub fn execute(args: Vec<String>) -> Result<()> {
-        let cli = SlugifyString::parse_from(args);
-        if !cli.text.is_empty() {
-            println!("{}", cli.parameters.slugify_string(cli.text.join(" "),)?);
+        let (cli, config) = crate::cli::parse_configured::<SlugifyString>(args.clone())?;
+        if cli.print_config {
+            return crate::cli::print_config::<SlugifyString>(&config, &args);
+        }
+        let slugifier = cli.parameters.slugifier()?;
+        if cli.reads_stdin() {
+            cli.slugify_input(
+                &slugifier,
+                std::io::stdin().lock(),
+                std::io::stdout().lock(),
+            )?;
+        } else {
+            println!("{}", slugifier.slugify(cli.text.join(" "))?);
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod slugify_string_tests {
+    use crate::{Result, SlugifyString};
+    use clap::Parser;
+
+    fn slugify_input(args: &[&str], input: &[u8]) -> Result<Vec<u8>> {
+        let cli = SlugifyString::parse_from(["slugify-string"].iter().chain(args.iter()));
+        let mut output = Vec::new();
+        cli.slugify_input(&cli.parameters.slugifier()?, input, &mut output)?;
+        Ok(output)
+    }
+
+    #[test]
+    fn test_reads_stdin_without_text_or_with_dash() {
+        assert!(SlugifyString::parse_from(["slugify-string"]).reads_stdin());
+        assert!(SlugifyString::parse_from(["slugify-string", "-"]).reads_stdin());
+        assert!(!SlugifyString::parse_from(["slugify-string", "Some Text"]).reads_stdin());
+    }
+
+    #[test]
+    fn test_slugify_input_modes() -> Result<()> {
+        assert_eq!(
+            slugify_input(&[], b"Some Text\nMore Text\n")?,
+            b"Some-Text-More-Text\n"
+        );
+        assert_eq!(
+            slugify_input(&["--lines"], b"Some Text\r\nMore Text\n")?,
+            b"Some-Text\nMore-Text\n"
+        );
+        assert_eq!(
+            slugify_input(&["-0"], b"Some\nText\0More Text\0")?,
+            b"Some-Text\0More-Text\0"
+        );
+        Ok(())
+    }
+}
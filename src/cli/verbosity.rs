@@ -1,5 +1,5 @@
-use clap::builder::PossibleValue;
 use clap::ValueEnum;
+use clap::builder::PossibleValue;
 
 use crate::heck_aliases;
 use serde::{Deserialize, Serialize};
@@ -71,12 +71,7 @@ impl Verbosity {
     }
     pub fn aliases(&self) -> Vec<String> {
         let mut aliases = Vec::<String>::new();
-        for alias in self
-            .variant_names()
-            .iter()
-            .map(|variant| heck_aliases(variant))
-            .flatten()
-        {
+        for alias in self.variant_names().iter().flat_map(heck_aliases) {
             if !aliases.contains(&alias) {
                 aliases.push(alias)
             }
@@ -122,9 +117,9 @@ impl ValueEnum for Verbosity {
                 };
                 alias == val
             }) {
-                return Ok(variant.clone());
+                return Ok(*variant);
             }
         }
-        return Err(val.to_string());
+        Err(val.to_string())
     }
 }
@@ -0,0 +1,439 @@
+use crate::cli::SlugifyFilenames;
+use crate::errors::{Error, Result};
+use crate::preset::{BUILTIN_PRESETS, Preset};
+use clap::builder::ArgAction;
+use clap::{Arg, ArgMatches, Command, CommandFactory};
+use iocore::Path;
+use std::collections::BTreeMap;
+use toml::{Table, Value};
+
+/// name of the project-level configuration file looked up from the
+/// current directory upwards
+pub const CONFIG_FILENAME: &str = "slugify.toml";
+
+/// ids of the command-line arguments which cannot be set from
+/// configuration files
+const UNSETTABLE: &[&str] = &["help", "version", "config", "print_config", "undo"];
+
+/// `Config` holds the settings read from configuration files, keyed by
+/// the long name of the command-line flag they set, e.g.:
+///
+/// ```toml
+/// separator = "_"
+/// case = "kebab"
+/// max-length = 64
+/// recursive = true
+/// on-conflict = "suffix"
+/// ignore = ["*.tmp", "build/"]
+/// replace = ["&= and ", "@=at"]
+///
+/// [profiles.dataset]
+/// separator = "_"
+/// ```
+///
+/// Command-line flags take precedence over configuration files, which
+/// provide their default values. The `profiles` table defines
+/// [`Preset`]s selected like the built-in ones with `preset`.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    files: Vec<Path>,
+    settings: Table,
+    profiles: BTreeMap<String, Preset>,
+}
+impl Config {
+    pub fn new() -> Config {
+        Config::default()
+    }
+    /// `from_str` parses the contents of the configuration file at
+    /// `path`, failing with [`Error::ConfigLoadError`] on invalid TOML
+    /// and with [`Error::ConfigError`] on unknown settings or settings
+    /// of the wrong type
+    pub fn from_str(path: &Path, contents: &str) -> Result<Config> {
+        let mut settings = contents
+            .parse::<Table>()
+            .map_err(|error| Error::ConfigLoadError(format!("{path}: {error}")))?;
+        let mut profiles = BTreeMap::new();
+        if let Some(value) = settings.remove("profiles") {
+            let Value::Table(table) = value else {
+                return Err(Error::ConfigError(format!(
+                    "{path}: profiles must be a table"
+                )));
+            };
+            for (name, profile) in table {
+                let preset = profile.try_into::<Preset>().map_err(|error| {
+                    Error::ConfigError(format!("{path}: profile {name:#?}: {error}"))
+                })?;
+                profiles.insert(name, preset);
+            }
+        }
+        // `lowercase` is stored as `case` so that `-l/--lowercase` and
+        // `--case` override it alike
+        if let Some(lowercase) = settings.remove("lowercase") {
+            let case = match lowercase {
+                Value::Boolean(_) if settings.contains_key("case") => {
+                    return Err(Error::ConfigError(format!(
+                        "{path}: lowercase and case cannot be set together"
+                    )));
+                }
+                Value::Boolean(true) => "lower",
+                Value::Boolean(false) => "preserve",
+                _ => {
+                    return Err(Error::ConfigError(format!(
+                        "{path}: lowercase must be a boolean"
+                    )));
+                }
+            };
+            settings.insert("case".to_string(), Value::String(case.to_string()));
+        }
+        let command = SlugifyFilenames::command();
+        for (key, value) in &settings {
+            let Some(arg) = settable_arg(&command, key) else {
+                return Err(Error::ConfigError(format!(
+                    "{path}: unknown setting {key:#?}"
+                )));
+            };
+            default_values(arg, value)
+                .map_err(|expected| Error::ConfigError(format!("{path}: {key} {expected}")))?;
+        }
+        Ok(Config {
+            files: vec![path.clone()],
+            settings,
+            profiles,
+        })
+    }
+    pub fn load(path: &Path) -> Result<Config> {
+        let contents = std::fs::read_to_string(path.to_path_buf())
+            .map_err(|error| Error::ConfigLoadError(format!("{path}: {error}")))?;
+        Config::from_str(path, &contents)
+    }
+    /// `discover` loads the user configuration file followed by the
+    /// closest `slugify.toml` from `directory` upwards, whose settings
+    /// take precedence
+    pub fn discover(directory: &Path) -> Result<Config> {
+        let mut config = Config::new();
+        for path in [user_config_path(), project_config_path(directory)]
+            .into_iter()
+            .flatten()
+        {
+            if path.is_file() {
+                config = config.merge(Config::load(&path)?);
+            }
+        }
+        Ok(config)
+    }
+    /// `merge` returns this configuration with the settings of `other`
+    /// replacing the ones it also sets
+    pub fn merge(mut self, other: Config) -> Config {
+        self.files.extend(other.files);
+        self.settings.extend(other.settings);
+        self.profiles.extend(other.profiles);
+        self
+    }
+    /// `preset` returns the profile named `name`, or else the built-in
+    /// preset of that name
+    pub fn preset(&self, name: &str) -> Result<Preset> {
+        if let Some(profile) = self.profiles.get(name) {
+            return Ok(profile.clone());
+        }
+        Preset::builtin(name).ok_or_else(|| {
+            let names = BUILTIN_PRESETS
+                .iter()
+                .map(|name| name.to_string())
+                .chain(self.profiles.keys().cloned())
+                .collect::<Vec<String>>()
+                .join(", ");
+            Error::ConfigError(format!(
+                "unknown preset {name:#?}, expected one of: {names}"
+            ))
+        })
+    }
+    /// `with_preset` merges the settings of the preset named `name`,
+    /// given on the command line, over the settings of the
+    /// configuration files. Without `name` the preset selected in the
+    /// configuration files, if any, is merged under their settings
+    /// instead.
+    pub fn with_preset(mut self, name: Option<&str>) -> Result<Config> {
+        match name {
+            Some(name) => {
+                let settings = self.preset(name)?.settings();
+                self.settings.extend(settings);
+            }
+            None => {
+                if let Some(Value::String(name)) = self.settings.get("preset") {
+                    let mut settings = self.preset(name)?.settings();
+                    settings.extend(std::mem::take(&mut self.settings));
+                    self.settings = settings;
+                }
+            }
+        }
+        Ok(self)
+    }
+    pub fn profiles(&self) -> &BTreeMap<String, Preset> {
+        &self.profiles
+    }
+    /// `files` are the configuration files read, in order of precedence
+    pub fn files(&self) -> &[Path] {
+        &self.files
+    }
+    pub fn settings(&self) -> &Table {
+        &self.settings
+    }
+    pub fn is_empty(&self) -> bool {
+        self.settings.is_empty()
+    }
+    /// `apply` sets the settings as default values of the matching
+    /// arguments of `command`, ignoring those it does not have
+    pub fn apply(&self, mut command: Command) -> Command {
+        for (key, value) in &self.settings {
+            let Some(id) = settable_arg(&command, key).map(|arg| arg.get_id().clone()) else {
+                continue;
+            };
+            command = command.mut_arg(id, |arg| match default_values(&arg, value) {
+                Ok(values) => arg.default_values(values),
+                Err(_) => arg,
+            });
+        }
+        command
+    }
+    /// `matches` parses `args` with the settings as defaults, failing
+    /// with [`Error::ConfigError`] when a setting has an invalid value
+    pub fn matches(&self, command: Command, args: &[String]) -> Result<ArgMatches> {
+        self.apply(command)
+            .try_get_matches_from(args)
+            .map_err(|error| {
+                if !error.use_stderr() {
+                    error.exit()
+                }
+                let files = self
+                    .files
+                    .iter()
+                    .map(|file| file.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                Error::ConfigError(format!(
+                    "{files}: {}",
+                    error.render().to_string().trim_end()
+                ))
+            })
+    }
+    /// `effective` returns every setting of `command` in effect after
+    /// parsing `args` with the settings as defaults, in the format of
+    /// configuration files
+    pub fn effective(&self, command: Command, args: &[String]) -> Result<Table> {
+        let matches = self.matches(command.clone(), args)?;
+        let mut effective = Table::new();
+        for arg in command.get_arguments().filter(|arg| is_settable(arg)) {
+            let Some(key) = arg.get_long() else {
+                continue;
+            };
+            let values = matches
+                .get_raw(arg.get_id().as_str())
+                .map(|values| {
+                    values
+                        .map(|value| value.to_string_lossy().to_string())
+                        .collect::<Vec<String>>()
+                })
+                .unwrap_or_default();
+            let value = match arg.get_action() {
+                ArgAction::SetTrue => Value::Boolean(values.iter().any(|value| value == "true")),
+                ArgAction::Append => Value::Array(values.into_iter().map(Value::String).collect()),
+                _ => match values.into_iter().next() {
+                    Some(value) if is_integer(arg) => match value.parse::<i64>() {
+                        Ok(integer) => Value::Integer(integer),
+                        Err(_) => Value::String(value),
+                    },
+                    Some(value) => Value::String(value),
+                    None => continue,
+                },
+            };
+            effective.insert(key.to_string(), value);
+        }
+        Ok(effective)
+    }
+}
+
+/// `project_config_path` returns the closest `slugify.toml` starting
+/// from `directory` and going upwards
+pub fn project_config_path(directory: &Path) -> Option<Path> {
+    directory
+        .try_canonicalize()
+        .to_path_buf()
+        .ancestors()
+        .map(|directory| Path::new(directory.display()).join(CONFIG_FILENAME))
+        .find(|path| path.is_file())
+}
+
+/// `user_config_path` returns `$XDG_CONFIG_HOME/slugify/config.toml`
+/// falling back to `~/.config/slugify/config.toml`
+pub fn user_config_path() -> Option<Path> {
+    let config_home = match std::env::var("XDG_CONFIG_HOME") {
+        Ok(config_home) if !config_home.is_empty() => Path::new(config_home),
+        _ => Path::new(std::env::var("HOME").ok()?).join(".config"),
+    };
+    Some(config_home.join("slugify").join("config.toml"))
+}
+
+fn settable_arg<'a>(command: &'a Command, key: &str) -> Option<&'a Arg> {
+    command
+        .get_arguments()
+        .find(|arg| arg.get_long() == Some(key) && is_settable(arg))
+}
+
+fn is_settable(arg: &Arg) -> bool {
+    arg.get_long().is_some()
+        && !UNSETTABLE.contains(&arg.get_id().as_str())
+        && matches!(
+            arg.get_action(),
+            ArgAction::Set | ArgAction::Append | ArgAction::SetTrue
+        )
+}
+
+fn is_integer(arg: &Arg) -> bool {
+    let type_id = arg.get_value_parser().type_id();
+    type_id == std::any::TypeId::of::<usize>() || type_id == std::any::TypeId::of::<u8>()
+}
+
+/// `default_values` converts `value` into the command-line values of
+/// `arg`, failing with a description of the expected type
+fn default_values(arg: &Arg, value: &Value) -> std::result::Result<Vec<String>, String> {
+    match (arg.get_action(), value) {
+        (ArgAction::SetTrue, Value::Boolean(boolean)) => Ok(vec![boolean.to_string()]),
+        (ArgAction::SetTrue, _) => Err("must be a boolean".to_string()),
+        (ArgAction::Append, Value::Array(values)) => values
+            .iter()
+            .map(|value| scalar(value).ok_or_else(|| "must be a list of strings".to_string()))
+            .collect(),
+        (ArgAction::Append, _) => Err("must be a list of strings".to_string()),
+        (_, value) => scalar(value)
+            .map(|value| vec![value])
+            .ok_or_else(|| "must be a string or a number".to_string()),
+    }
+}
+
+fn scalar(value: &Value) -> Option<String> {
+    match value {
+        Value::String(string) => Some(string.clone()),
+        Value::Integer(integer) => Some(integer.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use crate::{CONFIG_FILENAME, Config, Error, Result, SlugifyFilenames, project_config_path};
+    use clap::CommandFactory;
+    use iocore::Path;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        ["slugify-filenames"]
+            .iter()
+            .chain(args.iter())
+            .map(|arg| arg.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_command_line_flags_override_settings() -> Result<()> {
+        let config = Config::from_str(
+            &Path::new(CONFIG_FILENAME),
+            "separator = \"_\"\nlowercase = true\nmax-length = 12\nignore = [\"*.tmp\"]\n",
+        )?;
+        let effective = config.effective(SlugifyFilenames::command(), &args(&["-s", "."]))?;
+        assert_eq!(effective["separator"].as_str(), Some("."));
+        assert_eq!(effective["case"].as_str(), Some("lower"));
+        assert_eq!(effective["lowercase"].as_bool(), Some(false));
+        assert_eq!(effective["max-length"].as_integer(), Some(12));
+        assert_eq!(effective["recursive"].as_bool(), Some(false));
+        assert_eq!(
+            effective["ignore"]
+                .as_array()
+                .map(|patterns| patterns.len()),
+            Some(1)
+        );
+        assert!(!effective.contains_key("max-depth"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_settings_are_config_errors() {
+        let path = Path::new(CONFIG_FILENAME);
+        assert!(matches!(
+            Config::from_str(&path, "no-such-setting = 1"),
+            Err(Error::ConfigError(_))
+        ));
+        assert!(matches!(
+            Config::from_str(&path, "recursive = \"yes\""),
+            Err(Error::ConfigError(_))
+        ));
+        assert!(matches!(
+            Config::from_str(&path, "separator = "),
+            Err(Error::ConfigLoadError(_))
+        ));
+        let config = Config::from_str(&path, "extension = \"bogus\"").unwrap();
+        assert!(matches!(
+            config.matches(SlugifyFilenames::command(), &args(&[])),
+            Err(Error::ConfigError(_))
+        ));
+    }
+
+    #[test]
+    fn test_presets_from_the_command_line_override_settings() -> Result<()> {
+        let config = Config::from_str(
+            &Path::new(CONFIG_FILENAME),
+            "preset = \"dataset\"\nmax-length = 10\n[profiles.dataset]\nseparator = \"_\"\nmax-length = 100\n",
+        )?;
+        let effective = config
+            .clone()
+            .with_preset(None)?
+            .effective(SlugifyFilenames::command(), &args(&[]))?;
+        assert_eq!(effective["separator"].as_str(), Some("_"));
+        assert_eq!(effective["max-length"].as_integer(), Some(10));
+
+        let effective = config
+            .clone()
+            .with_preset(Some("python"))?
+            .effective(SlugifyFilenames::command(), &args(&["-s", "."]))?;
+        assert_eq!(effective["separator"].as_str(), Some("."));
+        assert_eq!(effective["allowed-chars"].as_str(), Some("a-zA-Z0-9_"));
+        assert_eq!(effective["max-length"].as_integer(), Some(10));
+
+        assert!(matches!(
+            config.with_preset(Some("no-such-preset")),
+            Err(Error::ConfigError(_))
+        ));
+        assert!(matches!(
+            Config::from_str(
+                &Path::new(CONFIG_FILENAME),
+                "[profiles.dataset]\nrecursive = true\n"
+            ),
+            Err(Error::ConfigError(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_config_is_discovered_upwards() -> Result<()> {
+        let root = Path::tmp().join("slugify-filenames-tests").join("config");
+        root.delete_unchecked();
+        root.join("a/b").mkdir_parents_unchecked();
+        root.join("a/b").mkdir_unchecked();
+        root.join(CONFIG_FILENAME)
+            .write_unchecked(b"separator = \"_\"\n");
+        root.join("a")
+            .join(CONFIG_FILENAME)
+            .write_unchecked(b"lowercase = true\n");
+        let root = root.canonicalize()?;
+
+        let path = project_config_path(&root.join("a/b")).expect("slugify.toml");
+        assert_eq!(
+            path.to_string(),
+            root.join("a").join(CONFIG_FILENAME).to_string()
+        );
+        let config = Config::load(&root.join(CONFIG_FILENAME))?.merge(Config::load(&path)?);
+        assert_eq!(config.files().len(), 2);
+        assert_eq!(config.settings().len(), 2);
+        assert!(project_config_path(&Path::new("/")).is_none());
+        root.delete_unchecked();
+        Ok(())
+    }
+}
@@ -1,20 +1,103 @@
+use iocore::Path;
 use serde::ser::SerializeStruct;
 use serde::{Serialize, Serializer};
 use std::fmt::Display;
+use std::io::ErrorKind;
+use std::sync::Arc;
+
+/// nothing went wrong, whether or not anything needed renaming
+pub const EXIT_SUCCESS: i32 = 0;
+/// an unexpected failure
+pub const EXIT_FAILURE: i32 = 1;
+/// `--check` found entries that would be renamed
+pub const EXIT_CHECK_FAILED: i32 = 2;
+/// a target exists or several entries slugify to the same name
+pub const EXIT_CONFLICT: i32 = 3;
+/// some entries could not be renamed, see `--keep-going`, or undone
+pub const EXIT_PARTIAL_FAILURE: i32 = 4;
+/// invalid arguments or patterns, after `EX_USAGE` of sysexits(3)
+pub const EXIT_USAGE: i32 = 64;
+/// a file could not be read, written or renamed, after `EX_IOERR`
+pub const EXIT_IO: i32 = 74;
+/// a configuration file could not be loaded, after `EX_CONFIG`
+pub const EXIT_CONFIG: i32 = 78;
 
 #[derive(Debug, Clone)]
 pub enum Error {
-    IOError(String),
-    PatternCompilationError(String),
+    /// renaming `from` into `to` would replace an existing entry
+    TargetExists {
+        from: Path,
+        to: Path,
+    },
+    /// several entries of one directory slugify into the same `target`
+    Collision {
+        target: Path,
+        sources: Vec<Path>,
+    },
+    PermissionDenied {
+        path: Path,
+        source: Arc<std::io::Error>,
+    },
+    /// `path` does not exist, `source` is the I/O error that told so
+    /// unless it was looked up beforehand
+    NotFound {
+        path: Path,
+        source: Option<Arc<std::io::Error>>,
+    },
+    /// `from` and `to` are on different filesystems
+    CrossDevice {
+        from: Path,
+        to: Path,
+    },
+    /// `pattern` failed to compile, `line` is its line number when it
+    /// was read from a file such as `.slugifyignore`
+    InvalidPattern {
+        line: Option<usize>,
+        pattern: String,
+        source: regex::Error,
+    },
+    /// undoing the journaled rename of `from` into `to` could lose data
+    UndoRefused {
+        from: Path,
+        to: Path,
+        reason: String,
+    },
+    /// `refused` renames of `journal` could not be undone
+    UndoIncomplete {
+        refused: usize,
+        journal: Path,
+    },
+    /// `path` is tracked by git and has uncommitted modifications
+    UncommittedChanges {
+        path: Path,
+    },
+    /// `command` exited unsuccessfully printing `message`
+    GitError {
+        command: String,
+        message: String,
+    },
+    /// `--check` found `count` entries which would be renamed
+    CheckFailed {
+        count: usize,
+    },
+    /// `failed` entries could not be renamed with `--keep-going`
+    PartialFailure {
+        failed: usize,
+    },
+    /// any other I/O error, about `path` when known
+    IOError {
+        path: Option<Path>,
+        source: Arc<std::io::Error>,
+    },
+    /// invalid command-line arguments, rendered along with the usage
+    UsageError(String),
     ConfigLoadError(String),
     ConfigError(String),
+    SerializationError(String),
 }
 
 impl Serialize for Error {
-    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
-    where
-        S: serde::Serializer,
-    {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
         let mut s = serializer.serialize_struct("Error", 2)?;
         s.serialize_field("variant", &self.variant())?;
         s.serialize_field("message", &format!("{}", self))?;
@@ -23,41 +106,255 @@ impl Serialize for Error {
 }
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(
-            f,
-            "{}{}",
-            self.variant(),
-            match self {
-                Self::IOError(e) => e.to_string(),
-                Self::PatternCompilationError(e) => e.to_string(),
-                Self::ConfigError(e) => e.to_string(),
-                Self::ConfigLoadError(e) => e.to_string(),
+        match self {
+            Self::TargetExists { from, to } => {
+                write!(f, "cannot rename {from} to {to}: target already exists")
+            }
+            Self::Collision { target, sources } => write!(
+                f,
+                "{} entries slugify to {target}: {}",
+                sources.len(),
+                sources
+                    .iter()
+                    .map(|source| format!("{:#?}", source.name()))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+            Self::PermissionDenied { path, .. } => write!(f, "permission denied: {path}"),
+            Self::NotFound { path, .. } => write!(f, "no such file or directory: {path}"),
+            Self::CrossDevice { from, to } => {
+                write!(f, "cannot rename {from} to {to}: different filesystems")
+            }
+            Self::InvalidPattern {
+                line,
+                pattern,
+                source,
+            } => match line {
+                Some(line) => write!(f, "invalid pattern {pattern:#?} on line {line}: {source}"),
+                None => write!(f, "invalid pattern {pattern:#?}: {source}"),
+            },
+            Self::UndoRefused { from, to, reason } => {
+                write!(f, "refusing to undo {from} -> {to}: {reason}")
             }
-        )
+            Self::UndoIncomplete { refused, journal } => write!(
+                f,
+                "{refused} rename(s) could not be undone and remain in {journal}"
+            ),
+            Self::UncommittedChanges { path } => write!(
+                f,
+                "refusing to rename {path}: it has uncommitted modifications, commit them or use --force"
+            ),
+            Self::GitError { command, message } => write!(f, "{command} failed: {message}"),
+            Self::CheckFailed { count } => write!(f, "{count} entries would be renamed"),
+            Self::PartialFailure { failed } => write!(f, "{failed} entries could not be renamed"),
+            Self::IOError {
+                path: Some(path),
+                source,
+            } => write!(f, "{path}: {source}"),
+            Self::IOError { path: None, source } => write!(f, "{source}"),
+            Self::UsageError(e) => write!(f, "{e}"),
+            Self::ConfigError(e) => write!(f, "{e}"),
+            Self::ConfigLoadError(e) => write!(f, "{e}"),
+            Self::SerializationError(e) => write!(f, "{e}"),
+        }
     }
 }
 
 impl Error {
     pub fn variant(&self) -> String {
         match self {
-            Error::IOError(_) => "IOError",
-            Error::PatternCompilationError(_) => "PatternCompilationError",
+            Error::TargetExists { .. } => "TargetExists",
+            Error::Collision { .. } => "Collision",
+            Error::PermissionDenied { .. } => "PermissionDenied",
+            Error::NotFound { .. } => "NotFound",
+            Error::CrossDevice { .. } => "CrossDevice",
+            Error::InvalidPattern { .. } => "InvalidPattern",
+            Error::UndoRefused { .. } => "UndoRefused",
+            Error::UndoIncomplete { .. } => "UndoIncomplete",
+            Error::UncommittedChanges { .. } => "UncommittedChanges",
+            Error::GitError { .. } => "GitError",
+            Error::CheckFailed { .. } => "CheckFailed",
+            Error::PartialFailure { .. } => "PartialFailure",
+            Error::IOError { .. } => "IOError",
+            Error::UsageError(_) => "UsageError",
             Error::ConfigError(_) => "ConfigError",
             Error::ConfigLoadError(_) => "ConfigLoadError",
+            Error::SerializationError(_) => "SerializationError",
         }
         .to_string()
     }
+    /// `io` attaches `path` to `error` picking the variant matching
+    /// its [`ErrorKind`]
+    pub fn io(path: &Path, error: std::io::Error) -> Error {
+        match error.kind() {
+            ErrorKind::PermissionDenied => Error::PermissionDenied {
+                path: path.clone(),
+                source: Arc::new(error),
+            },
+            ErrorKind::NotFound => Error::NotFound {
+                path: path.clone(),
+                source: Some(Arc::new(error)),
+            },
+            _ => Error::IOError {
+                path: Some(path.clone()),
+                source: Arc::new(error),
+            },
+        }
+    }
+    /// `not_found` is [`Error::NotFound`] for a `path` found missing
+    /// without any I/O error
+    pub fn not_found(path: &Path) -> Error {
+        Error::NotFound {
+            path: path.clone(),
+            source: None,
+        }
+    }
+    /// `rename` is [`Error::io`] for the failure to rename `from` into
+    /// `to`
+    pub fn rename(from: &Path, to: &Path, error: std::io::Error) -> Error {
+        match error.kind() {
+            ErrorKind::AlreadyExists | ErrorKind::DirectoryNotEmpty => Error::TargetExists {
+                from: from.clone(),
+                to: to.clone(),
+            },
+            ErrorKind::CrossesDevices => Error::CrossDevice {
+                from: from.clone(),
+                to: to.clone(),
+            },
+            _ => Error::io(from, error),
+        }
+    }
+    /// `invalid_pattern` is the failure to compile `pattern`
+    pub fn invalid_pattern(pattern: &str, source: regex::Error) -> Error {
+        Error::InvalidPattern {
+            line: None,
+            pattern: pattern.to_string(),
+            source,
+        }
+    }
+    /// `at_line` records the line number of an [`Error::InvalidPattern`]
+    /// and returns any other error unchanged
+    pub fn at_line(self, line: usize) -> Error {
+        match self {
+            Error::InvalidPattern {
+                pattern, source, ..
+            } => Error::InvalidPattern {
+                line: Some(line),
+                pattern,
+                source,
+            },
+            error => error,
+        }
+    }
+    /// `exit_code` is the status the command-line tools exit with when
+    /// failing with this error, see the `EXIT_` constants
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::TargetExists { .. }
+            | Error::Collision { .. }
+            | Error::UndoRefused { .. }
+            | Error::UncommittedChanges { .. } => EXIT_CONFLICT,
+            Error::CheckFailed { .. } => EXIT_CHECK_FAILED,
+            Error::PartialFailure { .. } | Error::UndoIncomplete { .. } => EXIT_PARTIAL_FAILURE,
+            Error::UsageError(_) | Error::ConfigError(_) | Error::InvalidPattern { .. } => {
+                EXIT_USAGE
+            }
+            Error::PermissionDenied { .. }
+            | Error::NotFound { .. }
+            | Error::CrossDevice { .. }
+            | Error::IOError { .. }
+            | Error::GitError { .. } => EXIT_IO,
+            Error::ConfigLoadError(_) => EXIT_CONFIG,
+            Error::SerializationError(_) => EXIT_FAILURE,
+        }
+    }
+    /// `kind` is the [`ErrorKind`] of I/O errors
+    pub fn kind(&self) -> Option<ErrorKind> {
+        match self {
+            Error::TargetExists { .. } => Some(ErrorKind::AlreadyExists),
+            Error::PermissionDenied { .. } => Some(ErrorKind::PermissionDenied),
+            Error::NotFound { .. } => Some(ErrorKind::NotFound),
+            Error::CrossDevice { .. } => Some(ErrorKind::CrossesDevices),
+            Error::IOError { source, .. } => Some(source.kind()),
+            _ => None,
+        }
+    }
 }
 
-impl std::error::Error for Error {}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::PermissionDenied { source, .. } | Error::IOError { source, .. } => {
+                Some(source.as_ref())
+            }
+            Error::NotFound {
+                source: Some(source),
+                ..
+            } => Some(source.as_ref()),
+            Error::InvalidPattern { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
 impl From<std::io::Error> for Error {
     fn from(e: std::io::Error) -> Self {
-        Error::IOError(format!("{}", e))
+        Error::IOError {
+            path: None,
+            source: Arc::new(e),
+        }
     }
 }
+/// iocore errors are plain messages, the [`ErrorKind`] is taken from
+/// the OS error code they mention or else from their variant, and the
+/// path from the first quoted string they mention
 impl From<iocore::Error> for Error {
     fn from(e: iocore::Error) -> Self {
-        Error::IOError(format!("{}", e))
+        let message = e.to_string();
+        let kind = os_error_code(&message)
+            .map(|code| std::io::Error::from_raw_os_error(code).kind())
+            .unwrap_or(match e {
+                iocore::Error::PathDoesNotExist(_) => ErrorKind::NotFound,
+                iocore::Error::MalformedGlobPattern(_)
+                | iocore::Error::MalformedFileName(_)
+                | iocore::Error::PathConversionError(_)
+                | iocore::Error::PatternMismatch(_)
+                | iocore::Error::ParseError(_) => ErrorKind::InvalidInput,
+                _ if message.contains("PathDoesNotExist") => ErrorKind::NotFound,
+                _ => ErrorKind::Other,
+            });
+        let path = quoted_path(&message);
+        let error = std::io::Error::new(kind, message);
+        match path {
+            Some(path) => Error::io(&path, error),
+            None => Error::from(error),
+        }
+    }
+}
+/// `os_error_code` is the N of the last "(os error N)" in `message`
+fn os_error_code(message: &str) -> Option<i32> {
+    let (_, code) = message.rsplit_once("(os error ")?;
+    code.split_once(')')?.0.parse().ok()
+}
+/// `quoted_path` is the first double-quoted string of `message`, as
+/// iocore prints paths with `{:#?}`
+fn quoted_path(message: &str) -> Option<Path> {
+    let start = message.find('"')?;
+    let mut escaped = false;
+    for (index, c) in message[start + 1..].char_indices() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => {
+                let quoted = &message[start..start + index + 2];
+                return serde_json::from_str::<String>(quoted).ok().map(Path::new);
+            }
+            _ => escaped = false,
+        }
+    }
+    None
+}
+impl From<clap::Error> for Error {
+    fn from(e: clap::Error) -> Self {
+        Error::UsageError(e.render().to_string().trim_end().to_string())
     }
 }
 impl From<fern::InitError> for Error {
@@ -65,9 +362,100 @@ impl From<fern::InitError> for Error {
         Error::ConfigError(format!("{}", e))
     }
 }
-impl From<regex::Error> for Error {
-    fn from(e: regex::Error) -> Self {
-        Error::PatternCompilationError(format!("{}", e))
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::SerializationError(format!("{}", e))
     }
 }
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod errors_tests {
+    use crate::Error;
+    use iocore::Path;
+    use std::io::ErrorKind;
+
+    #[test]
+    fn test_io_errors_keep_their_kind() {
+        let from = Path::new("/tmp/A B");
+        let to = Path::new("/tmp/A-B");
+        let error = |kind: ErrorKind| std::io::Error::from(kind);
+        assert!(matches!(
+            Error::io(&from, error(ErrorKind::PermissionDenied)),
+            Error::PermissionDenied { path, .. } if path.to_string() == "/tmp/A B"
+        ));
+        assert!(matches!(
+            Error::io(&from, error(ErrorKind::NotFound)),
+            Error::NotFound { .. }
+        ));
+        assert!(matches!(
+            Error::rename(&from, &to, error(ErrorKind::DirectoryNotEmpty)),
+            Error::TargetExists { .. }
+        ));
+        assert!(matches!(
+            Error::rename(&from, &to, error(ErrorKind::CrossesDevices)),
+            Error::CrossDevice { .. }
+        ));
+        assert_eq!(
+            Error::from(error(ErrorKind::Interrupted)).kind(),
+            Some(ErrorKind::Interrupted)
+        );
+    }
+
+    #[test]
+    fn test_not_found_keeps_its_source() {
+        use std::error::Error as _;
+        let error = Error::io(
+            &Path::new("/tmp/A B"),
+            std::io::Error::new(ErrorKind::NotFound, "gone"),
+        );
+        assert!(matches!(&error, Error::NotFound { .. }));
+        assert_eq!(
+            error.source().map(|source| source.to_string()).as_deref(),
+            Some("gone")
+        );
+        assert!(Error::not_found(&Path::new("/tmp/A B")).source().is_none());
+    }
+
+    #[test]
+    fn test_iocore_errors_keep_their_kind() {
+        let missing = Path::new("/tmp/slugify-filenames-does-not-exist/A B");
+        let error = Error::from(missing.read_bytes().expect_err("the file does not exist"));
+        assert!(
+            matches!(&error, Error::NotFound { path, .. } if path.to_string() == missing.to_string()),
+            "{error:#?}"
+        );
+        assert_eq!(error.kind(), Some(ErrorKind::NotFound));
+
+        let error = Error::from(iocore::Error::FileSystemError(
+            "ReadFile \"/root/a \\\"b\\\"\": Permission denied (os error 13)".to_string(),
+        ));
+        assert!(
+            matches!(&error, Error::PermissionDenied { path, .. } if path.to_string() == "/root/a \"b\""),
+            "{error:#?}"
+        );
+        assert_eq!(
+            Error::from(iocore::Error::MalformedGlobPattern("[".to_string())).kind(),
+            Some(ErrorKind::InvalidInput)
+        );
+    }
+
+    #[test]
+    fn test_exit_codes() {
+        let path = Path::new("/tmp/A B");
+        assert_eq!(
+            Error::TargetExists {
+                from: path.clone(),
+                to: Path::new("/tmp/A-B"),
+            }
+            .exit_code(),
+            3
+        );
+        assert_eq!(Error::PartialFailure { failed: 1 }.exit_code(), 4);
+        assert_eq!(Error::UsageError(String::new()).exit_code(), 64);
+        assert_eq!(
+            Error::io(&path, std::io::Error::from(ErrorKind::PermissionDenied)).exit_code(),
+            74
+        );
+    }
+}
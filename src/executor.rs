@@ -0,0 +1,257 @@
+use crate::errors::{Error, Result};
+use crate::filesystem::{Filesystem, RealFilesystem, is_case_only_rename};
+use crate::plan::{ConflictStrategy, RenameOperation, RenamePlan};
+use iocore::Path;
+
+/// `RenameOutcome` is what happened when an [`Executor`] carried out a
+/// [`RenameOperation`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RenameOutcome {
+    Renamed(Path),
+    /// the operation was not performed because of `dry_run`
+    WouldRename(Path),
+    Unchanged,
+}
+
+/// `Executor` carries out the operations of a [`RenamePlan`] produced
+/// by a [`crate::Planner`] on a [`Filesystem`]
+#[derive(Clone, Debug, Default)]
+pub struct Executor<F: Filesystem = RealFilesystem> {
+    filesystem: F,
+    dry_run: bool,
+    force: bool,
+    on_conflict: ConflictStrategy,
+}
+impl Executor {
+    pub fn new() -> Executor {
+        Executor::default()
+    }
+}
+impl<F: Filesystem> Executor<F> {
+    pub fn with_filesystem(filesystem: F) -> Executor<F> {
+        Executor {
+            filesystem,
+            dry_run: false,
+            force: false,
+            on_conflict: ConflictStrategy::default(),
+        }
+    }
+    pub fn dry_run(mut self, dry_run: bool) -> Executor<F> {
+        self.dry_run = dry_run;
+        self
+    }
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+    pub fn filesystem(&self) -> &F {
+        &self.filesystem
+    }
+    /// `force` overwrites targets that came into existence after
+    /// planning regardless of [`Executor::on_conflict`]
+    pub fn force(mut self, force: bool) -> Executor<F> {
+        self.force = force;
+        self
+    }
+    /// `on_conflict` should match the [`ConflictStrategy`] the plan was
+    /// made with, it decides what happens when a target exists
+    pub fn on_conflict(mut self, on_conflict: ConflictStrategy) -> Executor<F> {
+        self.on_conflict = on_conflict;
+        self
+    }
+    /// `execute` performs every operation of `plan` in order, stopping
+    /// at the first error
+    pub fn execute(&self, plan: &RenamePlan) -> Result<Vec<RenameOutcome>> {
+        plan.iter()
+            .map(|operation| self.execute_operation(operation))
+            .collect()
+    }
+    pub fn execute_operation(&self, operation: &RenameOperation) -> Result<RenameOutcome> {
+        if !operation.is_rename() {
+            return Ok(RenameOutcome::Unchanged);
+        }
+        self.rename(&operation.from, &operation.to)
+    }
+    pub fn rename(&self, from: &Path, to: &Path) -> Result<RenameOutcome> {
+        if self.dry_run {
+            return Ok(RenameOutcome::WouldRename(to.clone()));
+        }
+        let filesystem = &self.filesystem;
+        if filesystem.exists(from) && filesystem.exists(to) && !filesystem.is_same_entry(from, to) {
+            let overwrite = self.force || self.on_conflict == ConflictStrategy::Overwrite;
+            if filesystem.is_dir(from)
+                && filesystem.is_dir(to)
+                && (overwrite || self.on_conflict == ConflictStrategy::Merge)
+            {
+                self.merge_directory(from, to, overwrite)?;
+                return Ok(self.renamed(to));
+            }
+            if self.on_conflict == ConflictStrategy::Skip {
+                return Ok(RenameOutcome::Unchanged);
+            }
+            if !overwrite {
+                return Err(Error::TargetExists {
+                    from: from.clone(),
+                    to: to.clone(),
+                });
+            }
+        }
+        if is_case_only_rename(from, to) {
+            return self.rename_via_intermediate(from, to);
+        }
+        let new_path = filesystem.rename(from, to)?;
+        Ok(self.renamed(&new_path))
+    }
+    fn renamed(&self, new_path: &Path) -> RenameOutcome {
+        RenameOutcome::Renamed(
+            self.filesystem
+                .canonicalize(new_path)
+                .unwrap_or_else(|_| new_path.clone()),
+        )
+    }
+    /// `rename_via_intermediate` renames `from` into a temporary name
+    /// first so that case-only renames also happen on case-insensitive
+    /// filesystems, putting `from` back when the second step fails
+    fn rename_via_intermediate(&self, from: &Path, to: &Path) -> Result<RenameOutcome> {
+        let intermediate =
+            from.with_filename(format!(".{}.slugify-{}", from.name(), std::process::id()));
+        self.filesystem.rename(from, &intermediate)?;
+        match self.filesystem.rename(&intermediate, to) {
+            Ok(new_path) => Ok(self.renamed(&new_path)),
+            Err(error) => {
+                self.filesystem.rename(&intermediate, from)?;
+                Err(error)
+            }
+        }
+    }
+    /// `merge_directory` moves the contents of `from` into `to`,
+    /// merging subdirectories present in both and replacing clashing
+    /// files only when `overwrite` is true, then removes `from`
+    fn merge_directory(&self, from: &Path, to: &Path, overwrite: bool) -> Result<()> {
+        self.check_merge(from, to, overwrite)?;
+        self.move_children(from, to)
+    }
+    /// `check_merge` fails with the first entry of `from` which cannot
+    /// be moved into `to`, before [`Executor::move_children`] moves
+    /// anything: files clashing without `overwrite` and entries whose
+    /// kind differs from the one they would replace
+    fn check_merge(&self, from: &Path, to: &Path, overwrite: bool) -> Result<()> {
+        for child in self.filesystem.list(from)? {
+            let target = to.join(child.name());
+            if !self.filesystem.exists(&target) {
+                continue;
+            }
+            let (child_is_dir, target_is_dir) = (
+                self.filesystem.is_dir(&child),
+                self.filesystem.is_dir(&target),
+            );
+            if child_is_dir && target_is_dir {
+                self.check_merge(&child, &target, overwrite)?;
+            } else if !overwrite || child_is_dir != target_is_dir {
+                return Err(Error::TargetExists {
+                    from: child.clone(),
+                    to: target.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+    fn move_children(&self, from: &Path, to: &Path) -> Result<()> {
+        for child in self.filesystem.list(from)? {
+            let target = to.join(child.name());
+            if self.filesystem.is_dir(&child) && self.filesystem.is_dir(&target) {
+                self.move_children(&child, &target)?;
+            } else {
+                self.filesystem.rename(&child, &target)?;
+            }
+        }
+        self.filesystem.remove_dir(from)
+    }
+}
+
+#[cfg(test)]
+mod executor_tests {
+    use crate::{
+        ConflictStrategy, Error, Executor, Filesystem, MemoryFilesystem, RenameOutcome, Result,
+    };
+    use iocore::Path;
+
+    #[test]
+    fn test_case_only_rename_leaves_no_intermediate() -> Result<()> {
+        let filesystem = MemoryFilesystem::case_insensitive();
+        let root = Path::new("/virtual/executor-case-only");
+        filesystem.write(&root.join("Foo.txt"), b"contents");
+
+        let outcome = Executor::with_filesystem(&filesystem)
+            .rename(&root.join("Foo.txt"), &root.join("foo.txt"))?;
+        assert_eq!(outcome, RenameOutcome::Renamed(root.join("foo.txt")));
+        assert_eq!(
+            filesystem
+                .list(&root)?
+                .iter()
+                .map(|path| path.name())
+                .collect::<Vec<String>>(),
+            vec!["foo.txt".to_string()]
+        );
+        assert_eq!(filesystem.read(&root.join("FOO.TXT"))?, b"contents");
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_directory_checks_clashes_before_moving_anything() -> Result<()> {
+        let filesystem = MemoryFilesystem::new();
+        let root = Path::new("/virtual/executor-merge-clash");
+        filesystem.write(&root.join("Some Dir/a.txt"), b"a");
+        filesystem.write(&root.join("Some Dir/Sub/b.txt"), b"b");
+        filesystem.write(&root.join("Some-Dir/Sub/b.txt"), b"existing");
+        let before = filesystem.paths(&root);
+
+        let error = Executor::with_filesystem(&filesystem)
+            .on_conflict(ConflictStrategy::Merge)
+            .rename(&root.join("Some Dir"), &root.join("Some-Dir"))
+            .unwrap_err();
+        assert!(
+            matches!(error, Error::TargetExists { to, .. } if to == root.join("Some-Dir/Sub/b.txt"))
+        );
+        assert_eq!(filesystem.paths(&root), before);
+
+        // overwriting replaces files but never a directory with a file
+        filesystem.write(&root.join("Some-Dir/a.txt/c.txt"), b"c");
+        let before = filesystem.paths(&root);
+        assert!(
+            Executor::with_filesystem(&filesystem)
+                .on_conflict(ConflictStrategy::Overwrite)
+                .rename(&root.join("Some Dir"), &root.join("Some-Dir"))
+                .is_err()
+        );
+        assert_eq!(filesystem.paths(&root), before);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_directory_on_memory_filesystem() -> Result<()> {
+        let filesystem = MemoryFilesystem::new();
+        let root = Path::new("/virtual/executor-merge");
+        filesystem.write(&root.join("Some Dir/Sub/a.txt"), b"a");
+        filesystem.write(&root.join("Some-Dir/Sub/b.txt"), b"b");
+
+        Executor::with_filesystem(&filesystem)
+            .on_conflict(ConflictStrategy::Merge)
+            .rename(&root.join("Some Dir"), &root.join("Some-Dir"))?;
+        assert!(!filesystem.exists(&root.join("Some Dir")));
+        assert_eq!(
+            filesystem
+                .paths(&root)
+                .iter()
+                .map(|path| path.to_string())
+                .collect::<Vec<String>>(),
+            vec![
+                "/virtual/executor-merge/Some-Dir",
+                "/virtual/executor-merge/Some-Dir/Sub",
+                "/virtual/executor-merge/Some-Dir/Sub/a.txt",
+                "/virtual/executor-merge/Some-Dir/Sub/b.txt",
+            ]
+        );
+        assert_eq!(filesystem.read(&root.join("Some-Dir/Sub/a.txt"))?, b"a");
+        Ok(())
+    }
+}
@@ -0,0 +1,582 @@
+use crate::errors::{Error, Result};
+use iocore::Path;
+use std::cell::{Cell, RefCell};
+use std::collections::BTreeMap;
+use std::io::{ErrorKind, Write};
+
+/// `Filesystem` is every operation the [`crate::Planner`], the
+/// [`crate::Executor`] and the [`crate::Journal`] perform on the
+/// filesystem so that they can be driven against [`MemoryFilesystem`]
+/// as well as [`RealFilesystem`]
+pub trait Filesystem: std::fmt::Debug {
+    fn exists(&self, path: &Path) -> bool;
+    fn is_dir(&self, path: &Path) -> bool;
+    fn is_file(&self, path: &Path) -> bool {
+        self.exists(path) && !self.is_dir(path)
+    }
+    /// `list` returns the entries of `directory` sorted by name
+    fn list(&self, directory: &Path) -> Result<Vec<Path>>;
+    fn canonicalize(&self, path: &Path) -> Result<Path>;
+    fn read(&self, path: &Path) -> Result<Vec<u8>>;
+    /// `write` replaces the contents of the file `path`
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()>;
+    /// `append` adds `contents` at the end of the file `path`,
+    /// creating it when missing
+    fn append(&self, path: &Path, contents: &[u8]) -> Result<()>;
+    /// `remove_file` deletes the file `path`
+    fn remove_file(&self, path: &Path) -> Result<()>;
+    /// `rename` moves `from` into `to` creating the missing parent
+    /// directories of `to` and replacing `to` when it is a file
+    fn rename(&self, from: &Path, to: &Path) -> Result<Path>;
+    /// `check_rename` fails when `path` must not be renamed at all, the
+    /// [`crate::Planner`] calls it for every entry it plans to rename
+    /// so that such entries fail before anything is renamed
+    fn check_rename(&self, _path: &Path) -> Result<()> {
+        Ok(())
+    }
+    /// `remove_dir` removes the empty directory `path`
+    fn remove_dir(&self, path: &Path) -> Result<()>;
+    /// `fingerprint` describes `path` well enough to tell whether it
+    /// changed later on
+    fn fingerprint(&self, path: &Path) -> Result<Fingerprint>;
+    /// `is_same_entry` tells whether `a` and `b` lead to the same
+    /// entry, e.g.: two spellings of a name on a case-insensitive
+    /// filesystem
+    fn is_same_entry(&self, a: &Path, b: &Path) -> bool;
+    /// `is_case_insensitive` probes whether names inside `directory`
+    /// are matched regardless of case by looking up one of its entries
+    /// with the case of its name swapped, which never writes anything.
+    /// A directory without any entry whose name has cased letters is
+    /// reported as case-sensitive since no case-only clash can happen
+    /// in it.
+    fn is_case_insensitive(&self, directory: &Path) -> bool {
+        let Ok(entries) = self.list(directory) else {
+            return false;
+        };
+        for entry in entries {
+            let name = entry.name();
+            let swapped = swap_case(&name);
+            if swapped != name {
+                let probe = directory.join(&swapped);
+                return self.exists(&probe) && self.is_same_entry(&entry, &probe);
+            }
+        }
+        false
+    }
+}
+impl<T: Filesystem + ?Sized> Filesystem for &T {
+    fn exists(&self, path: &Path) -> bool {
+        (**self).exists(path)
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        (**self).is_dir(path)
+    }
+    fn is_file(&self, path: &Path) -> bool {
+        (**self).is_file(path)
+    }
+    fn list(&self, directory: &Path) -> Result<Vec<Path>> {
+        (**self).list(directory)
+    }
+    fn canonicalize(&self, path: &Path) -> Result<Path> {
+        (**self).canonicalize(path)
+    }
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        (**self).read(path)
+    }
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        (**self).write(path, contents)
+    }
+    fn append(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        (**self).append(path, contents)
+    }
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        (**self).remove_file(path)
+    }
+    fn rename(&self, from: &Path, to: &Path) -> Result<Path> {
+        (**self).rename(from, to)
+    }
+    fn check_rename(&self, path: &Path) -> Result<()> {
+        (**self).check_rename(path)
+    }
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        (**self).remove_dir(path)
+    }
+    fn fingerprint(&self, path: &Path) -> Result<Fingerprint> {
+        (**self).fingerprint(path)
+    }
+    fn is_same_entry(&self, a: &Path, b: &Path) -> bool {
+        (**self).is_same_entry(a, b)
+    }
+    fn is_case_insensitive(&self, directory: &Path) -> bool {
+        (**self).is_case_insensitive(directory)
+    }
+}
+
+/// `RealFilesystem` is the [`Filesystem`] of the machine
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RealFilesystem;
+impl Filesystem for RealFilesystem {
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        path.is_dir()
+    }
+    fn is_file(&self, path: &Path) -> bool {
+        path.is_file()
+    }
+    fn list(&self, directory: &Path) -> Result<Vec<Path>> {
+        let mut entries = std::fs::read_dir(directory.to_path_buf())
+            .and_then(|entries| {
+                entries
+                    .map(|entry| Ok(directory.join(entry?.file_name().to_string_lossy())))
+                    .collect::<std::io::Result<Vec<Path>>>()
+            })
+            .map_err(|error| Error::io(directory, error))?;
+        entries.sort_by_key(|entry| entry.name());
+        Ok(entries)
+    }
+    fn canonicalize(&self, path: &Path) -> Result<Path> {
+        std::fs::canonicalize(path.to_path_buf())
+            .map(|canonical| Path::new(canonical.to_string_lossy()))
+            .map_err(|error| Error::io(path, error))
+    }
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        std::fs::read(path.to_path_buf()).map_err(|error| Error::io(path, error))
+    }
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        std::fs::write(path.to_path_buf(), contents).map_err(|error| Error::io(path, error))
+    }
+    fn append(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path.to_path_buf())
+            .and_then(|mut file| {
+                file.write_all(contents)?;
+                file.flush()
+            })
+            .map_err(|error| Error::io(path, error))
+    }
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        std::fs::remove_file(path.to_path_buf()).map_err(|error| Error::io(path, error))
+    }
+    fn rename(&self, from: &Path, to: &Path) -> Result<Path> {
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent.to_path_buf())
+                .map_err(|error| Error::io(&parent, error))?;
+        }
+        std::fs::rename(from.to_path_buf(), to.to_path_buf())
+            .map_err(|error| Error::rename(from, to, error))?;
+        Ok(to.clone())
+    }
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        std::fs::remove_dir(path.to_path_buf()).map_err(|error| Error::io(path, error))
+    }
+    fn fingerprint(&self, path: &Path) -> Result<Fingerprint> {
+        let metadata =
+            std::fs::metadata(path.to_path_buf()).map_err(|error| Error::io(path, error))?;
+        if metadata.is_dir() {
+            return Ok(Fingerprint::directory());
+        }
+        Ok(Fingerprint {
+            is_dir: false,
+            size: Some(metadata.len()),
+            modified: metadata
+                .modified()
+                .ok()
+                .map(|modified| humantime::format_rfc3339_nanos(modified).to_string()),
+        })
+    }
+    fn is_same_entry(&self, a: &Path, b: &Path) -> bool {
+        is_same_entry(a, b)
+    }
+}
+
+/// `Fingerprint` is what [`Filesystem::fingerprint`] knows about an
+/// entry, the size and modification time of directories are left out
+/// since they change as their children get renamed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Fingerprint {
+    pub is_dir: bool,
+    pub size: Option<u64>,
+    pub modified: Option<String>,
+}
+impl Fingerprint {
+    pub fn directory() -> Fingerprint {
+        Fingerprint {
+            is_dir: true,
+            ..Fingerprint::default()
+        }
+    }
+}
+
+/// `MemoryFilesystem` is a [`Filesystem`] living in memory, optionally
+/// case-insensitive, meant to exercise the library against virtual
+/// trees.
+///
+/// *Example*
+///
+/// ```
+/// use iocore::Path;
+/// use slugify_filenames::{Filesystem, MemoryFilesystem};
+///
+/// let filesystem = MemoryFilesystem::new();
+/// filesystem.write(&Path::new("/virtual/Some Dir/A File.txt"), b"contents");
+/// assert!(filesystem.is_dir(&Path::new("/virtual/Some Dir")));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MemoryFilesystem {
+    /// keyed by [`MemoryFilesystem::key`]
+    entries: RefCell<BTreeMap<String, MemoryEntry>>,
+    case_insensitive: bool,
+    /// counts writes, standing in for modification times
+    clock: Cell<u64>,
+}
+#[derive(Clone, Debug)]
+struct MemoryEntry {
+    /// the path as spelled when the entry was created or renamed
+    path: Path,
+    /// `None` for directories
+    contents: Option<Vec<u8>>,
+    /// the value of [`MemoryFilesystem::clock`] when the file was last
+    /// written
+    modified: u64,
+}
+impl MemoryFilesystem {
+    pub fn new() -> MemoryFilesystem {
+        MemoryFilesystem::default()
+    }
+    /// `case_insensitive` returns a [`MemoryFilesystem`] which folds the
+    /// case of names like the default filesystems of macOS and Windows
+    pub fn case_insensitive() -> MemoryFilesystem {
+        MemoryFilesystem {
+            case_insensitive: true,
+            ..MemoryFilesystem::default()
+        }
+    }
+    /// `create_dir` creates `path` and its missing parents
+    pub fn create_dir(&self, path: &Path) {
+        for ancestor in ancestors(path) {
+            self.entries
+                .borrow_mut()
+                .entry(self.key(&ancestor))
+                .or_insert(MemoryEntry {
+                    path: ancestor,
+                    contents: None,
+                    modified: 0,
+                });
+        }
+    }
+    /// `write` creates or replaces the file `path` and creates its
+    /// missing parents
+    pub fn write(&self, path: &Path, contents: &[u8]) {
+        if let Some(parent) = path.parent() {
+            self.create_dir(&parent);
+        }
+        self.clock.set(self.clock.get() + 1);
+        self.entries.borrow_mut().insert(
+            self.key(path),
+            MemoryEntry {
+                path: path.clone(),
+                contents: Some(contents.to_vec()),
+                modified: self.clock.get(),
+            },
+        );
+    }
+    /// `with_files` creates the empty `files`, relative to
+    /// `directory`, along with their missing parents
+    pub fn with_files<T: AsRef<str>>(
+        self,
+        directory: &Path,
+        files: impl IntoIterator<Item = T>,
+    ) -> MemoryFilesystem {
+        for file in files {
+            self.write(&directory.join(file.as_ref()), &[]);
+        }
+        self
+    }
+    /// `paths` returns every entry below `directory` sorted by path
+    pub fn paths(&self, directory: &Path) -> Vec<Path> {
+        let prefix = format!("{}/", self.key(directory).trim_end_matches('/'));
+        self.entries
+            .borrow()
+            .iter()
+            .filter(|(key, _)| key.starts_with(&prefix))
+            .map(|(_, entry)| entry.path.clone())
+            .collect()
+    }
+    fn key(&self, path: &Path) -> String {
+        let key = path.to_string();
+        let key = if key.len() > 1 {
+            key.trim_end_matches('/').to_string()
+        } else {
+            key
+        };
+        if self.case_insensitive {
+            key.to_lowercase()
+        } else {
+            key
+        }
+    }
+    fn is_root(&self, path: &Path) -> bool {
+        self.key(path) == "/"
+    }
+    fn entry(&self, path: &Path) -> Option<MemoryEntry> {
+        self.entries.borrow().get(&self.key(path)).cloned()
+    }
+    fn not_found(&self, path: &Path) -> Error {
+        Error::not_found(path)
+    }
+}
+impl Filesystem for MemoryFilesystem {
+    fn exists(&self, path: &Path) -> bool {
+        self.is_root(path) || self.entry(path).is_some()
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        self.is_root(path) || matches!(self.entry(path), Some(MemoryEntry { contents: None, .. }))
+    }
+    fn list(&self, directory: &Path) -> Result<Vec<Path>> {
+        if !self.is_dir(directory) {
+            return Err(Error::io(directory, ErrorKind::NotADirectory.into()));
+        }
+        let parent = self.key(directory);
+        let mut entries = self
+            .entries
+            .borrow()
+            .values()
+            .filter(|entry| {
+                entry
+                    .path
+                    .parent()
+                    .is_some_and(|entry_parent| self.key(&entry_parent) == parent)
+                    && !self.is_root(&entry.path)
+            })
+            .map(|entry| entry.path.clone())
+            .collect::<Vec<Path>>();
+        entries.sort_by_key(|entry| entry.name());
+        Ok(entries)
+    }
+    fn canonicalize(&self, path: &Path) -> Result<Path> {
+        match self.entry(path) {
+            Some(entry) => Ok(entry.path),
+            None if self.is_root(path) => Ok(path.clone()),
+            None => Err(self.not_found(path)),
+        }
+    }
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        match self.entry(path) {
+            Some(MemoryEntry {
+                contents: Some(contents),
+                ..
+            }) => Ok(contents),
+            Some(_) => Err(Error::io(path, ErrorKind::IsADirectory.into())),
+            None => Err(self.not_found(path)),
+        }
+    }
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        if self.is_dir(path) {
+            return Err(Error::io(path, ErrorKind::IsADirectory.into()));
+        }
+        MemoryFilesystem::write(self, path, contents);
+        Ok(())
+    }
+    fn append(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        let mut existing = if self.exists(path) {
+            self.read(path)?
+        } else {
+            Vec::new()
+        };
+        existing.extend_from_slice(contents);
+        Filesystem::write(self, path, &existing)
+    }
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        if !self.exists(path) {
+            return Err(self.not_found(path));
+        }
+        if self.is_dir(path) {
+            return Err(Error::io(path, ErrorKind::IsADirectory.into()));
+        }
+        self.entries.borrow_mut().remove(&self.key(path));
+        Ok(())
+    }
+    fn rename(&self, from: &Path, to: &Path) -> Result<Path> {
+        if !self.exists(from) {
+            return Err(self.not_found(from));
+        }
+        if self.exists(to) && !self.is_same_entry(from, to) {
+            if self.is_dir(to) && !self.list(to)?.is_empty() {
+                return Err(Error::rename(from, to, ErrorKind::DirectoryNotEmpty.into()));
+            }
+            if self.is_dir(to) != self.is_dir(from) {
+                return Err(Error::rename(from, to, ErrorKind::AlreadyExists.into()));
+            }
+            self.entries.borrow_mut().remove(&self.key(to));
+        }
+        if let Some(parent) = to.parent() {
+            self.create_dir(&parent);
+        }
+        let from_key = self.key(from);
+        let prefix = format!("{from_key}/");
+        let moved = self
+            .entries
+            .borrow()
+            .iter()
+            .filter(|(key, _)| **key == from_key || key.starts_with(&prefix))
+            .map(|(key, entry)| (key.clone(), entry.clone()))
+            .collect::<Vec<(String, MemoryEntry)>>();
+        let mut entries = self.entries.borrow_mut();
+        for (key, _) in &moved {
+            entries.remove(key);
+        }
+        let from_length = from.to_string().trim_end_matches('/').len();
+        for (_, entry) in moved {
+            let suffix = entry.path.to_string()[from_length..].to_string();
+            let path = Path::new(format!("{}{suffix}", to.to_string().trim_end_matches('/')));
+            entries.insert(self.key(&path), MemoryEntry { path, ..entry });
+        }
+        Ok(to.clone())
+    }
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        if !self.is_dir(path) {
+            return Err(Error::io(path, ErrorKind::NotADirectory.into()));
+        }
+        if !self.list(path)?.is_empty() {
+            return Err(Error::io(path, ErrorKind::DirectoryNotEmpty.into()));
+        }
+        self.entries.borrow_mut().remove(&self.key(path));
+        Ok(())
+    }
+    fn fingerprint(&self, path: &Path) -> Result<Fingerprint> {
+        match self.entry(path) {
+            Some(MemoryEntry {
+                contents: Some(contents),
+                modified,
+                ..
+            }) => Ok(Fingerprint {
+                is_dir: false,
+                size: Some(contents.len() as u64),
+                modified: Some(modified.to_string()),
+            }),
+            Some(_) => Ok(Fingerprint::directory()),
+            None if self.is_root(path) => Ok(Fingerprint::directory()),
+            None => Err(self.not_found(path)),
+        }
+    }
+    fn is_same_entry(&self, a: &Path, b: &Path) -> bool {
+        self.exists(a) && self.key(a) == self.key(b)
+    }
+    fn is_case_insensitive(&self, _directory: &Path) -> bool {
+        self.case_insensitive
+    }
+}
+
+/// `ancestors` returns `path` and its parents, outermost first
+fn ancestors(path: &Path) -> Vec<Path> {
+    let mut ancestors = vec![path.clone()];
+    let mut ancestor = path.parent();
+    while let Some(directory) = ancestor {
+        let parent = directory.parent();
+        if parent
+            .as_ref()
+            .is_some_and(|parent| parent.to_string() == directory.to_string())
+            || directory.to_string() == "/"
+        {
+            break;
+        }
+        ancestors.insert(0, directory);
+        ancestor = parent;
+    }
+    ancestors
+}
+
+/// `is_case_insensitive` probes `directory` on the
+/// [`RealFilesystem`], see [`Filesystem::is_case_insensitive`]
+pub fn is_case_insensitive(directory: &Path) -> bool {
+    RealFilesystem.is_case_insensitive(directory)
+}
+
+/// `is_case_only_rename` is true when `from` and `to` are in the same
+/// directory and their names only differ by case
+pub fn is_case_only_rename(from: &Path, to: &Path) -> bool {
+    let (from_name, to_name) = (from.name(), to.name());
+    from_name != to_name
+        && from_name.to_lowercase() == to_name.to_lowercase()
+        && from.parent().map(|parent| parent.to_string())
+            == to.parent().map(|parent| parent.to_string())
+}
+
+/// `is_same_entry` tells whether `a` and `b` lead to the same entry
+/// of the filesystem, e.g.: two spellings of a name on a
+/// case-insensitive filesystem
+#[cfg(unix)]
+pub fn is_same_entry(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (
+        std::fs::symlink_metadata(a.to_path_buf()),
+        std::fs::symlink_metadata(b.to_path_buf()),
+    ) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+/// `is_same_entry` tells whether `a` and `b` lead to the same entry
+/// of the filesystem, e.g.: two spellings of a name on a
+/// case-insensitive filesystem
+#[cfg(not(unix))]
+pub fn is_same_entry(a: &Path, b: &Path) -> bool {
+    a.exists() && b.exists() && a.to_string().to_lowercase() == b.to_string().to_lowercase()
+}
+
+fn swap_case(name: &str) -> String {
+    name.chars()
+        .flat_map(|c| {
+            if c.is_lowercase() {
+                c.to_uppercase().collect::<Vec<char>>()
+            } else {
+                c.to_lowercase().collect::<Vec<char>>()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod filesystem_tests {
+    use crate::{is_case_insensitive, is_case_only_rename};
+    use iocore::Path;
+
+    #[test]
+    fn test_is_case_only_rename() {
+        assert!(is_case_only_rename(
+            &Path::new("/tmp/Foo"),
+            &Path::new("/tmp/foo")
+        ));
+        assert!(!is_case_only_rename(
+            &Path::new("/tmp/foo"),
+            &Path::new("/tmp/foo")
+        ));
+        assert!(!is_case_only_rename(
+            &Path::new("/tmp/Foo"),
+            &Path::new("/var/foo")
+        ));
+        assert!(!is_case_only_rename(
+            &Path::new("/tmp/Foo"),
+            &Path::new("/tmp/bar")
+        ));
+    }
+
+    #[test]
+    fn test_probe_does_not_write() {
+        let root = Path::tmp()
+            .join("slugify-filenames-tests")
+            .join("case-probe");
+        root.delete_unchecked();
+        root.mkdir_parents_unchecked();
+        assert!(!is_case_insensitive(&root));
+        root.join("Foo").write_unchecked(&[]);
+        let case_insensitive = root.join("FOO").exists();
+        assert_eq!(is_case_insensitive(&root), case_insensitive);
+        assert_eq!(root.list().unwrap().len(), 1);
+        root.delete_unchecked();
+    }
+}
@@ -0,0 +1,365 @@
+use crate::errors::{Error, Result};
+use crate::filesystem::{Filesystem, Fingerprint, RealFilesystem};
+use crate::ignore::git_work_tree;
+use iocore::Path;
+use std::cell::RefCell;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// `GitFilesystem` is the [`RealFilesystem`] except that entries
+/// tracked by git are renamed the way `git mv` does, updating the index
+/// so that history follows them. Untracked entries and entries outside
+/// of any git working tree get plain renames.
+///
+/// The tracked and modified entries of every working tree are read
+/// once, the first time an entry of it is checked or renamed, and kept
+/// up to date as entries are renamed.
+#[derive(Clone, Debug, Default)]
+pub struct GitFilesystem {
+    force: bool,
+    work_trees: RefCell<BTreeMap<String, WorkTreeStatus>>,
+}
+impl GitFilesystem {
+    pub fn new() -> GitFilesystem {
+        GitFilesystem::default()
+    }
+    /// `force` renames tracked entries with uncommitted modifications
+    /// rather than failing with [`Error::UncommittedChanges`]
+    pub fn force(mut self, force: bool) -> GitFilesystem {
+        self.force = force;
+        self
+    }
+    /// `status` runs `f` with the working tree containing `path` and
+    /// its status, or returns `None` outside of any working tree
+    fn status<T>(
+        &self,
+        path: &Path,
+        f: impl FnOnce(&Path, &mut WorkTreeStatus) -> T,
+    ) -> Result<Option<T>> {
+        let Some(work_tree) = path.parent().and_then(|parent| git_work_tree(&parent)) else {
+            return Ok(None);
+        };
+        let mut work_trees = self.work_trees.borrow_mut();
+        let status = match work_trees.entry(work_tree.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(WorkTreeStatus::read(&work_tree)?),
+        };
+        Ok(Some(f(&work_tree, status)))
+    }
+}
+impl Filesystem for GitFilesystem {
+    fn exists(&self, path: &Path) -> bool {
+        RealFilesystem.exists(path)
+    }
+    fn is_dir(&self, path: &Path) -> bool {
+        RealFilesystem.is_dir(path)
+    }
+    fn is_file(&self, path: &Path) -> bool {
+        RealFilesystem.is_file(path)
+    }
+    fn list(&self, directory: &Path) -> Result<Vec<Path>> {
+        RealFilesystem.list(directory)
+    }
+    fn canonicalize(&self, path: &Path) -> Result<Path> {
+        RealFilesystem.canonicalize(path)
+    }
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        RealFilesystem.read(path)
+    }
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        RealFilesystem.write(path, contents)
+    }
+    fn append(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        RealFilesystem.append(path, contents)
+    }
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        RealFilesystem.remove_file(path)
+    }
+    fn rename(&self, from: &Path, to: &Path) -> Result<Path> {
+        self.check_rename(from)?;
+        let Some((work_tree, true)) = self.status(from, |work_tree, status| {
+            (work_tree.clone(), status.is_tracked(from))
+        })?
+        else {
+            RealFilesystem.rename(from, to)?;
+            self.status(from, |_, status| status.rename(from, to))?;
+            return Ok(to.clone());
+        };
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent.to_path_buf())
+                .map_err(|error| Error::io(&parent, error))?;
+        }
+        git(
+            &work_tree,
+            &["mv", "--", &from.to_string(), &to.to_string()],
+        )?;
+        self.status(from, |_, status| status.rename(from, to))?;
+        Ok(to.clone())
+    }
+    fn check_rename(&self, path: &Path) -> Result<()> {
+        let modified = self.status(path, |_, status| status.is_modified(path))?;
+        if !self.force && modified == Some(true) {
+            return Err(Error::UncommittedChanges { path: path.clone() });
+        }
+        Ok(())
+    }
+    fn remove_dir(&self, path: &Path) -> Result<()> {
+        RealFilesystem.remove_dir(path)
+    }
+    fn fingerprint(&self, path: &Path) -> Result<Fingerprint> {
+        RealFilesystem.fingerprint(path)
+    }
+    fn is_same_entry(&self, a: &Path, b: &Path) -> bool {
+        RealFilesystem.is_same_entry(a, b)
+    }
+    fn is_case_insensitive(&self, directory: &Path) -> bool {
+        RealFilesystem.is_case_insensitive(directory)
+    }
+}
+
+/// `WorkTreeStatus` holds the tracked entries of a git working tree
+/// and those with uncommitted modifications, as absolute paths
+#[derive(Clone, Debug, Default)]
+struct WorkTreeStatus {
+    tracked: BTreeSet<String>,
+    modified: BTreeSet<String>,
+}
+impl WorkTreeStatus {
+    /// `read` runs `git ls-files` and `git status` once in `work_tree`
+    fn read(work_tree: &Path) -> Result<WorkTreeStatus> {
+        let tracked = git(work_tree, &["ls-files", "-z"])?
+            .split('\0')
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| work_tree.join(entry).to_string())
+            .collect();
+        let mut modified = BTreeSet::new();
+        let status = git(work_tree, &["status", "--porcelain", "-z"])?;
+        let mut entries = status.split('\0').filter(|entry| !entry.is_empty());
+        while let Some(entry) = entries.next() {
+            let (code, path) = entry.split_at_checked(3).unwrap_or((entry, ""));
+            if code.starts_with('R') || code.starts_with('C') {
+                // the original path of renames and copies follows
+                entries.next();
+            }
+            if !code.starts_with("??") && !code.starts_with("!!") {
+                modified.insert(work_tree.join(path).to_string());
+            }
+        }
+        Ok(WorkTreeStatus { tracked, modified })
+    }
+    /// `is_tracked` tells whether `path`, or anything below it when it
+    /// is a directory, is in the index
+    fn is_tracked(&self, path: &Path) -> bool {
+        contains_or_below(&self.tracked, path)
+    }
+    /// `is_modified` tells whether `path`, or any tracked entry below
+    /// it when it is a directory, differs from `HEAD`
+    fn is_modified(&self, path: &Path) -> bool {
+        contains_or_below(&self.modified, path)
+    }
+    /// `rename` moves the entries at or below `from` to `to`
+    fn rename(&mut self, from: &Path, to: &Path) {
+        for paths in [&mut self.tracked, &mut self.modified] {
+            let moved = paths
+                .iter()
+                .filter_map(|path| Some((path.clone(), relocated(path, from, to)?)))
+                .collect::<Vec<(String, String)>>();
+            for (path, new_path) in moved {
+                paths.remove(&path);
+                paths.insert(new_path);
+            }
+        }
+    }
+}
+
+/// `contains_or_below` tells whether `paths` has `path` or an entry
+/// below it
+fn contains_or_below(paths: &BTreeSet<String>, path: &Path) -> bool {
+    let path = path.to_string();
+    let prefix = format!("{}/", path.trim_end_matches('/'));
+    paths.contains(&path)
+        || paths
+            .range(prefix.clone()..)
+            .next()
+            .is_some_and(|entry| entry.starts_with(&prefix))
+}
+
+/// `relocated` is `path` moved from below `from` to below `to`, or
+/// `None` when it is not at or below `from`
+fn relocated(path: &str, from: &Path, to: &Path) -> Option<String> {
+    let from = from.to_string();
+    if path == from {
+        return Some(to.to_string());
+    }
+    let below = path.strip_prefix(&format!("{}/", from.trim_end_matches('/')))?;
+    Some(to.join(below).to_string())
+}
+
+/// `is_tracked` tells whether `path`, or anything below it when it is
+/// a directory, is in the index of the repository at `work_tree`
+pub fn is_tracked(work_tree: &Path, path: &Path) -> Result<bool> {
+    Ok(!git(work_tree, &["ls-files", "-z", "--", &path.to_string()])?.is_empty())
+}
+
+/// `has_uncommitted_changes` tells whether `path`, or any tracked
+/// entry below it when it is a directory, differs from `HEAD` either in
+/// the index or in the working tree. Untracked and ignored entries do
+/// not count.
+pub fn has_uncommitted_changes(work_tree: &Path, path: &Path) -> Result<bool> {
+    Ok(git(
+        work_tree,
+        &["status", "--porcelain", "-z", "--", &path.to_string()],
+    )?
+    .split('\0')
+    .any(|entry| !entry.is_empty() && !entry.starts_with("??") && !entry.starts_with("!!")))
+}
+
+/// `git` runs `git` with `args` in `work_tree` returning its stdout
+fn git(work_tree: &Path, args: &[&str]) -> Result<String> {
+    let output = std::process::Command::new("git")
+        .arg("-C")
+        .arg(work_tree.to_string())
+        .args(args)
+        .output()
+        .map_err(|error| Error::io(work_tree, error))?;
+    if !output.status.success() {
+        return Err(Error::GitError {
+            command: format!("git {}", args.join(" ")),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod git_tests {
+    use crate::{
+        Error, Executor, GitFilesystem, IgnoreStack, Planner, Result, SlugOptions,
+        has_uncommitted_changes, is_tracked,
+    };
+    use iocore::Path;
+
+    fn git(root: &Path, args: &[&str]) -> String {
+        let output = std::process::Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .arg("-C")
+            .arg(root.to_string())
+            .args(args)
+            .output()
+            .expect("git");
+        assert!(output.status.success(), "git {args:?}");
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    /// `repository` creates a git repository named `name` with `files`
+    /// committed
+    fn repository(name: &str, files: &[&str]) -> Result<Path> {
+        let root = Path::tmp().join("slugify-filenames-tests").join(name);
+        root.delete_unchecked();
+        root.mkdir_parents_unchecked();
+        let root = root.canonicalize()?;
+        for file in files {
+            root.join(file).write_unchecked(b"tracked");
+        }
+        git(&root, &["init", "-q"]);
+        git(&root, &["add", "."]);
+        git(&root, &["commit", "-q", "-m", "initial"]);
+        Ok(root)
+    }
+
+    #[test]
+    fn test_tracked_entries_are_renamed_in_the_index() -> Result<()> {
+        let root = repository("git", &["Tracked File.txt", "Modified File.txt"])?;
+        root.join("Modified File.txt").write_unchecked(b"modified");
+        root.join("Untracked File.txt")
+            .write_unchecked(b"untracked");
+
+        assert!(is_tracked(&root, &root.join("Tracked File.txt"))?);
+        assert!(!is_tracked(&root, &root.join("Untracked File.txt"))?);
+        assert!(has_uncommitted_changes(
+            &root,
+            &root.join("Modified File.txt")
+        )?);
+        assert!(!has_uncommitted_changes(
+            &root,
+            &root.join("Untracked File.txt")
+        )?);
+
+        let executor = Executor::with_filesystem(GitFilesystem::new());
+        executor.rename(
+            &root.join("Tracked File.txt"),
+            &root.join("tracked-file.txt"),
+        )?;
+        executor.rename(
+            &root.join("Untracked File.txt"),
+            &root.join("untracked-file.txt"),
+        )?;
+        assert!(matches!(
+            executor.rename(
+                &root.join("Modified File.txt"),
+                &root.join("modified-file.txt")
+            ),
+            Err(Error::UncommittedChanges { .. })
+        ));
+        assert!(root.join("untracked-file.txt").exists());
+        assert_eq!(
+            git(&root, &["status", "--porcelain"]),
+            [
+                " M \"Modified File.txt\"",
+                "R  \"Tracked File.txt\" -> tracked-file.txt",
+                "?? untracked-file.txt",
+                ""
+            ]
+            .join("\n")
+        );
+
+        Executor::with_filesystem(GitFilesystem::new().force(true)).rename(
+            &root.join("Modified File.txt"),
+            &root.join("modified-file.txt"),
+        )?;
+        assert!(git(&root, &["ls-files"]).contains("modified-file.txt"));
+        root.delete_unchecked();
+        Ok(())
+    }
+
+    #[test]
+    fn test_modified_entries_fail_while_planning() -> Result<()> {
+        let root = repository(
+            "git-planning",
+            &[
+                "A Tracked File.txt",
+                "B Modified File.txt",
+                "C Tracked File.txt",
+            ],
+        )?;
+        root.join("B Modified File.txt")
+            .write_unchecked(b"modified");
+        let status = git(&root, &["status", "--porcelain"]);
+        let filesystem = GitFilesystem::new();
+        let planner =
+            Planner::with_filesystem(&filesystem, SlugOptions::new().build()?).recursive(true);
+        let targets = [(root.clone(), IgnoreStack::new())];
+
+        assert!(matches!(
+            planner.plan(&targets),
+            Err(Error::UncommittedChanges { path }) if path.name() == "B Modified File.txt"
+        ));
+        assert_eq!(git(&root, &["status", "--porcelain"]), status);
+
+        let (plan, failures) = planner.plan_keep_going(&targets)?;
+        assert_eq!(failures.len(), 1);
+        Executor::with_filesystem(&filesystem).execute(&plan)?;
+        assert_eq!(
+            git(&root, &["status", "--porcelain"]),
+            [
+                "R  \"A Tracked File.txt\" -> A-Tracked-File.txt",
+                " M \"B Modified File.txt\"",
+                "R  \"C Tracked File.txt\" -> C-Tracked-File.txt",
+                ""
+            ]
+            .join("\n")
+        );
+        root.delete_unchecked();
+        Ok(())
+    }
+}
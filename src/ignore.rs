@@ -0,0 +1,601 @@
+use crate::errors::{Error, Result};
+use crate::filesystem::{Filesystem, RealFilesystem};
+use iocore::Path;
+use regex::Regex;
+
+pub const SLUGIFY_IGNORE_FILENAME: &str = ".slugifyignore";
+pub const GIT_IGNORE_FILENAME: &str = ".gitignore";
+pub const IGNORE_FILENAME: &str = ".ignore";
+/// `.git` directories are never walked into nor renamed
+pub const GIT_DIRECTORY_NAME: &str = ".git";
+
+/// `IgnorePattern` is a single line of a `.slugifyignore` file
+/// following the semantics of `.gitignore` patterns:
+///
+/// - blank lines and lines starting with `#` are skipped
+/// - `!` negates the pattern, re-including previously ignored paths
+/// - a trailing `/` only matches directories
+/// - a leading or middle `/` anchors the pattern to the directory
+///   containing the ignore file, otherwise it matches at any depth
+/// - `*`, `?` and `[...]` do not match `/` whereas `**` does
+#[derive(Clone, Debug)]
+pub struct IgnorePattern {
+    source: String,
+    negated: bool,
+    directory_only: bool,
+    anchored: bool,
+    regex: Regex,
+}
+impl IgnorePattern {
+    /// `parse` returns `Ok(None)` for blank lines and comments
+    pub fn parse(line: &str) -> Result<Option<IgnorePattern>> {
+        let source = trim_unescaped_trailing_whitespace(line.trim_start());
+        if source.is_empty() || source.starts_with('#') {
+            return Ok(None);
+        }
+        let (negated, pattern) = match source.strip_prefix('!') {
+            Some(pattern) => (true, pattern.to_string()),
+            None => (false, source.to_string()),
+        };
+        let (directory_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern.to_string()),
+            None => (false, pattern),
+        };
+        if pattern.is_empty() {
+            return Ok(None);
+        }
+        let anchored = pattern.contains('/');
+        let pattern = pattern.trim_start_matches('/').to_string();
+        let body = glob_to_regex(&pattern);
+        let regex = if anchored {
+            Regex::new(&format!("^{body}$"))
+        } else {
+            Regex::new(&format!("^(?:.*/)?{body}$"))
+        }
+        .map_err(|error| Error::invalid_pattern(&source, error))?;
+        Ok(Some(IgnorePattern {
+            source,
+            negated,
+            directory_only,
+            anchored,
+            regex,
+        }))
+    }
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+    pub fn is_negated(&self) -> bool {
+        self.negated
+    }
+    pub fn is_directory_only(&self) -> bool {
+        self.directory_only
+    }
+    pub fn is_anchored(&self) -> bool {
+        self.anchored
+    }
+    /// `matches` takes a `/`-separated path relative to the directory
+    /// containing the ignore file
+    pub fn matches(&self, relative_path: &str, is_dir: bool) -> bool {
+        if self.directory_only && !is_dir {
+            return false;
+        }
+        self.regex.is_match(relative_path.trim_start_matches('/'))
+    }
+}
+
+/// `IgnoreRules` is an ordered list of [`IgnorePattern`] relative to
+/// a base directory where, like in `.gitignore`, the last matching
+/// pattern wins.
+#[derive(Clone, Debug)]
+pub struct IgnoreRules {
+    base: Path,
+    patterns: Vec<IgnorePattern>,
+}
+impl IgnoreRules {
+    pub fn new(base: &Path) -> IgnoreRules {
+        IgnoreRules {
+            base: base.try_canonicalize(),
+            patterns: Vec::new(),
+        }
+    }
+    pub fn from_lines<T: std::fmt::Display>(
+        base: &Path,
+        lines: impl IntoIterator<Item = T>,
+    ) -> Result<IgnoreRules> {
+        let mut rules = IgnoreRules::new(base);
+        for (index, line) in lines.into_iter().enumerate() {
+            rules
+                .add_line(&line.to_string())
+                .map_err(|error| error.at_line(index + 1))?;
+        }
+        Ok(rules)
+    }
+    /// `from_file` reads the patterns in `path` relative to its parent directory
+    pub fn from_file(path: &Path) -> Result<IgnoreRules> {
+        let path = path.try_canonicalize();
+        let base = path.parent().unwrap_or_else(Path::cwd);
+        IgnoreRules::from_lines(&base, path.read()?.lines())
+    }
+    pub fn add_line(&mut self, line: &str) -> Result<()> {
+        if let Some(pattern) = IgnorePattern::parse(line)? {
+            self.patterns.push(pattern);
+        }
+        Ok(())
+    }
+    pub fn base(&self) -> &Path {
+        &self.base
+    }
+    pub fn patterns(&self) -> &[IgnorePattern] {
+        &self.patterns
+    }
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+    /// `relative_path` returns the `/`-separated path of `path`
+    /// relative to [`IgnoreRules::base`], or `None` when `path` is
+    /// outside of it
+    pub fn relative_path(&self, path: &Path) -> Option<String> {
+        let path = path.try_absolute();
+        let relative = path
+            .to_path_buf()
+            .strip_prefix(self.base.to_path_buf())
+            .ok()?
+            .to_path_buf();
+        let parts = relative
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join("/"))
+        }
+    }
+    /// `matched` returns `Some(true)` when the last pattern matching
+    /// `path` ignores it, `Some(false)` when it is a negated pattern
+    /// and `None` when no pattern matches
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let absolute = path.try_absolute().to_string();
+        let relative = self.relative_path(path);
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| {
+                pattern.source() == absolute
+                    || relative
+                        .as_ref()
+                        .map(|relative| pattern.matches(relative, is_dir))
+                        .unwrap_or_default()
+            })
+            .map(|pattern| !pattern.is_negated())
+    }
+    /// `matched_relative` behaves like [`IgnoreRules::matched`] for a
+    /// path already relative to [`IgnoreRules::base`]
+    pub fn matched_relative(&self, relative_path: &str, is_dir: bool) -> Option<bool> {
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(relative_path, is_dir))
+            .map(|pattern| !pattern.is_negated())
+    }
+    /// `is_ignored` also considers the ancestors of `path` because,
+    /// as in `.gitignore`, a path cannot be re-included when one of
+    /// its parent directories is ignored
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if let Some(relative) = self.relative_path(path) {
+            let parts = relative.split('/').collect::<Vec<&str>>();
+            for index in 1..parts.len() {
+                if self.matched_relative(&parts[..index].join("/"), true) == Some(true) {
+                    return true;
+                }
+            }
+        }
+        self.matched(path, is_dir) == Some(true)
+    }
+}
+
+/// `IgnoreStack` holds the [`IgnoreRules`] in effect for a directory
+/// being walked: the top-level rules followed by the rules of every
+/// ignore file found on the way down, the deepest of which take
+/// precedence just like nested `.gitignore` files.
+#[derive(Clone, Debug)]
+pub struct IgnoreStack {
+    rules: Vec<IgnoreRules>,
+    filenames: Vec<String>,
+}
+impl Default for IgnoreStack {
+    fn default() -> IgnoreStack {
+        IgnoreStack {
+            rules: Vec::new(),
+            filenames: vec![SLUGIFY_IGNORE_FILENAME.to_string()],
+        }
+    }
+}
+impl IgnoreStack {
+    pub fn new() -> IgnoreStack {
+        IgnoreStack::default()
+    }
+    pub fn from_rules(rules: IgnoreRules) -> IgnoreStack {
+        let mut stack = IgnoreStack::new();
+        stack.push(rules);
+        stack
+    }
+    /// `with_vcs_ignores` makes the stack also read `.gitignore` and
+    /// `.ignore` files while walking, as well as `.git/info/exclude`
+    /// and the global git excludes file of the repository containing
+    /// `path` along with the `.gitignore` files of the directories
+    /// between the root of that repository and `path`.
+    ///
+    /// The rules already in the stack keep precedence over the ones
+    /// added by this method.
+    pub fn with_vcs_ignores(&self, path: &Path) -> Result<IgnoreStack> {
+        let mut stack = IgnoreStack {
+            rules: Vec::new(),
+            filenames: vec![
+                GIT_IGNORE_FILENAME.to_string(),
+                IGNORE_FILENAME.to_string(),
+                SLUGIFY_IGNORE_FILENAME.to_string(),
+            ],
+        };
+        let path = path.try_canonicalize();
+        if let Some(root) = git_work_tree(&path) {
+            if let Some(excludes) = global_git_excludes_path()
+                && excludes.is_file()
+            {
+                stack.push(IgnoreRules::from_lines(&root, excludes.read()?.lines())?);
+            }
+            let exclude = root.join(GIT_DIRECTORY_NAME).join("info").join("exclude");
+            if exclude.is_file() {
+                stack.push(IgnoreRules::from_lines(&root, exclude.read()?.lines())?);
+            }
+            let mut ancestors = Vec::new();
+            let mut ancestor = path.parent();
+            while let Some(directory) = ancestor {
+                if !directory.to_path_buf().starts_with(root.to_path_buf()) {
+                    break;
+                }
+                ancestors.insert(0, directory.clone());
+                ancestor = directory.parent();
+            }
+            for directory in ancestors {
+                for filename in [GIT_IGNORE_FILENAME, IGNORE_FILENAME] {
+                    let ignore_file = directory.join(filename);
+                    if ignore_file.is_file() {
+                        stack.push(IgnoreRules::from_file(&ignore_file)?);
+                    }
+                }
+            }
+        }
+        for rules in self.rules.iter() {
+            stack.push(rules.clone());
+        }
+        Ok(stack)
+    }
+    pub fn push(&mut self, rules: IgnoreRules) {
+        if !rules.is_empty() {
+            self.rules.push(rules);
+        }
+    }
+    pub fn rules(&self) -> &[IgnoreRules] {
+        &self.rules
+    }
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+    /// `with_directory` returns a copy of this stack extended with the
+    /// ignore files inside `directory`, if any, whose patterns only
+    /// apply to that subtree
+    pub fn with_directory(&self, directory: &Path) -> Result<IgnoreStack> {
+        self.with_renamed_directory(directory, directory)
+    }
+    /// `with_renamed_directory` behaves like
+    /// [`IgnoreStack::with_directory`] for a directory that has been
+    /// renamed from `original` so that patterns keep matching the
+    /// names they were written for
+    pub fn with_renamed_directory(&self, directory: &Path, original: &Path) -> Result<IgnoreStack> {
+        self.with_renamed_directory_on(&RealFilesystem, directory, original)
+    }
+    /// `with_directory_on` behaves like [`IgnoreStack::with_directory`]
+    /// reading ignore files from `filesystem`
+    pub fn with_directory_on<F: Filesystem>(
+        &self,
+        filesystem: &F,
+        directory: &Path,
+    ) -> Result<IgnoreStack> {
+        self.with_renamed_directory_on(filesystem, directory, directory)
+    }
+    fn with_renamed_directory_on<F: Filesystem>(
+        &self,
+        filesystem: &F,
+        directory: &Path,
+        original: &Path,
+    ) -> Result<IgnoreStack> {
+        let mut stack = self.clone();
+        for filename in self.filenames.iter() {
+            let path = directory.join(filename);
+            if filesystem.is_file(&path) {
+                let contents = String::from_utf8_lossy(&filesystem.read(&path)?).to_string();
+                stack.push(IgnoreRules::from_lines(original, contents.lines())?);
+            }
+        }
+        Ok(stack)
+    }
+    /// `matched` consults the deepest rules first and returns the
+    /// first decision found, see [`IgnoreRules::matched`]
+    pub fn matched(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        self.rules
+            .iter()
+            .rev()
+            .find_map(|rules| rules.matched(path, is_dir))
+    }
+    /// `is_ignored` is meant to be called on every entry visited while
+    /// walking, ancestors are only considered for entries outside of
+    /// the walk, see [`IgnoreRules::is_ignored`]
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.filenames.contains(&path.name()) || is_always_ignored(path) {
+            return true;
+        }
+        match self.matched(path, is_dir) {
+            Some(ignored) => ignored,
+            None => self
+                .rules
+                .iter()
+                .any(|rules| rules.is_ignored(path, is_dir)),
+        }
+    }
+}
+
+/// `is_always_ignored` is true for `.git` directories regardless of
+/// any ignore rules or `--force`
+pub fn is_always_ignored(path: &Path) -> bool {
+    path.name() == GIT_DIRECTORY_NAME
+}
+
+/// `git_work_tree` returns the closest directory containing a `.git`
+/// entry starting from `path` and going upwards
+pub fn git_work_tree(path: &Path) -> Option<Path> {
+    // `Path::parent` never returns `None`, so the ancestors come from
+    // `std::path::Path` which stops at the root
+    path.try_canonicalize()
+        .to_path_buf()
+        .ancestors()
+        .map(|directory| Path::new(directory.display()))
+        .find(|directory| directory.join(GIT_DIRECTORY_NAME).exists())
+}
+
+/// `global_git_excludes_path` returns the value of
+/// `core.excludesFile` falling back to `$XDG_CONFIG_HOME/git/ignore`
+pub fn global_git_excludes_path() -> Option<Path> {
+    if let Ok(output) = std::process::Command::new("git")
+        .args(["config", "--path", "--get", "core.excludesFile"])
+        .output()
+        && output.status.success()
+    {
+        let path = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !path.is_empty() {
+            return Some(Path::new(path));
+        }
+    }
+    match std::env::var("XDG_CONFIG_HOME") {
+        Ok(config_home) if !config_home.is_empty() => {
+            Some(Path::new(config_home).join("git").join("ignore"))
+        }
+        _ => std::env::var("HOME")
+            .ok()
+            .map(|home| Path::new(home).join(".config").join("git").join("ignore")),
+    }
+}
+
+fn trim_unescaped_trailing_whitespace(line: &str) -> String {
+    let mut trimmed = line.trim_end().to_string();
+    if trimmed.ends_with('\\') && line.len() > trimmed.len() {
+        trimmed.push(' ');
+    }
+    trimmed
+}
+
+/// `glob_to_regex` translates a gitignore glob into the body of a regex
+fn glob_to_regex(glob: &str) -> String {
+    let chars = glob.chars().collect::<Vec<char>>();
+    let mut regex = String::new();
+    let mut index = 0;
+    while index < chars.len() {
+        let c = chars[index];
+        match c {
+            '*' if chars.get(index + 1) == Some(&'*') => {
+                let at_start = index == 0 || chars[index - 1] == '/';
+                let followed_by_slash = chars.get(index + 2) == Some(&'/');
+                let at_end = index + 2 == chars.len();
+                if at_start && followed_by_slash {
+                    regex.push_str("(?:.*/)?");
+                    index += 3;
+                } else if at_start && at_end {
+                    regex.push_str(".*");
+                    index += 2;
+                } else {
+                    regex.push_str("[^/]*");
+                    index += 2;
+                }
+                continue;
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => match chars[index + 1..].iter().position(|c| *c == ']') {
+                Some(offset) if offset > 0 => {
+                    let class = chars[index + 1..index + 1 + offset]
+                        .iter()
+                        .collect::<String>();
+                    let class = match class.strip_prefix('!') {
+                        Some(class) => format!("^{}", class.replace('\\', "\\\\")),
+                        None => class.replace('\\', "\\\\"),
+                    };
+                    regex.push_str(&format!("[{class}]"));
+                    index += offset + 2;
+                    continue;
+                }
+                _ => regex.push_str(r"\["),
+            },
+            '\\' if index + 1 < chars.len() => {
+                regex.push_str(&regex::escape(&chars[index + 1].to_string()));
+                index += 2;
+                continue;
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+        index += 1;
+    }
+    regex
+}
+
+#[cfg(test)]
+mod ignore_tests {
+    use crate::{Error, IgnorePattern, IgnoreRules, IgnoreStack, Result, git_work_tree};
+    use iocore::Path;
+
+    fn rules(lines: &[&str]) -> Result<IgnoreRules> {
+        IgnoreRules::from_lines(&Path::new("/base"), lines.iter())
+    }
+
+    #[test]
+    fn test_git_work_tree_stops_at_the_root() {
+        assert!(git_work_tree(&Path::new("/")).is_none());
+    }
+
+    #[test]
+    fn test_ignore_pattern_skips_comments_and_blank_lines() -> Result<()> {
+        assert!(IgnorePattern::parse("")?.is_none());
+        assert!(IgnorePattern::parse("   ")?.is_none());
+        assert!(IgnorePattern::parse("# comment")?.is_none());
+        assert!(
+            IgnorePattern::parse(r"\#file")?
+                .unwrap()
+                .matches("#file", false)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_pattern_wildcards() -> Result<()> {
+        let pattern = IgnorePattern::parse("*.tmp")?.unwrap();
+        assert!(pattern.matches("foo.tmp", false));
+        assert!(pattern.matches("a/b/foo.tmp", false));
+        assert!(!pattern.matches("foo.tmp.txt", false));
+
+        let pattern = IgnorePattern::parse("file?.[ch]")?.unwrap();
+        assert!(pattern.matches("file1.c", false));
+        assert!(pattern.matches("src/fileA.h", false));
+        assert!(!pattern.matches("file10.c", false));
+
+        let pattern = IgnorePattern::parse("[!a]*")?.unwrap();
+        assert!(pattern.matches("bar", false));
+        assert!(!pattern.matches("apple", false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_pattern_double_asterisk() -> Result<()> {
+        let pattern = IgnorePattern::parse("**/node_modules")?.unwrap();
+        assert!(pattern.matches("node_modules", true));
+        assert!(pattern.matches("web/app/node_modules", true));
+
+        let pattern = IgnorePattern::parse("docs/**/*.md")?.unwrap();
+        assert!(pattern.matches("docs/README.md", false));
+        assert!(pattern.matches("docs/a/b/README.md", false));
+        assert!(!pattern.matches("other/docs/README.md", false));
+
+        let pattern = IgnorePattern::parse("build/**")?.unwrap();
+        assert!(pattern.matches("build/a/b", false));
+        assert!(!pattern.matches("build", true));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_pattern_directory_only_and_anchored() -> Result<()> {
+        let pattern = IgnorePattern::parse("cache/")?.unwrap();
+        assert!(pattern.is_directory_only());
+        assert!(pattern.matches("cache", true));
+        assert!(pattern.matches("a/cache", true));
+        assert!(!pattern.matches("cache", false));
+
+        let pattern = IgnorePattern::parse("/TODO")?.unwrap();
+        assert!(pattern.is_anchored());
+        assert!(pattern.matches("TODO", false));
+        assert!(!pattern.matches("sub/TODO", false));
+
+        let pattern = IgnorePattern::parse("doc/frotz")?.unwrap();
+        assert!(pattern.is_anchored());
+        assert!(pattern.matches("doc/frotz", true));
+        assert!(!pattern.matches("a/doc/frotz", true));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_rules_report_the_line_of_invalid_patterns() {
+        assert!(matches!(
+            rules(&["*.log", "# comment", "[z-a].txt"]),
+            Err(Error::InvalidPattern { line: Some(3), pattern, .. }) if pattern == "[z-a].txt"
+        ));
+    }
+
+    #[test]
+    fn test_ignore_rules_last_match_wins() -> Result<()> {
+        let rules = rules(&["*.log", "!important.log", "# comment", "debug/"])?;
+        assert_eq!(rules.len(), 3);
+        assert_eq!(rules.matched(&Path::new("/base/a.log"), false), Some(true));
+        assert_eq!(
+            rules.matched(&Path::new("/base/sub/important.log"), false),
+            Some(false)
+        );
+        assert_eq!(rules.matched(&Path::new("/base/a.txt"), false), None);
+        assert!(rules.is_ignored(&Path::new("/base/a.log"), false));
+        assert!(!rules.is_ignored(&Path::new("/base/important.log"), false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_rules_cannot_reinclude_inside_ignored_directory() -> Result<()> {
+        let rules = rules(&["debug/", "!debug/keep.txt"])?;
+        assert!(rules.is_ignored(&Path::new("/base/debug"), true));
+        assert!(rules.is_ignored(&Path::new("/base/debug/keep.txt"), false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_rules_keep_exact_path_compatibility() -> Result<()> {
+        let rules = rules(&["Some File.txt", "sub/Other File.txt", "/elsewhere/x y"])?;
+        assert!(rules.is_ignored(&Path::new("/base/a/Some File.txt"), false));
+        assert!(rules.is_ignored(&Path::new("/base/sub/Other File.txt"), false));
+        assert!(rules.is_ignored(&Path::new("/elsewhere/x y"), false));
+        assert!(!rules.is_ignored(&Path::new("/elsewhere/Other File.txt"), false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_stack_deeper_rules_take_precedence() -> Result<()> {
+        let mut stack = IgnoreStack::from_rules(rules(&["*.log", "/sub/generated"])?);
+        stack.push(IgnoreRules::from_lines(
+            &Path::new("/base/sub"),
+            ["!keep.log", "*.bak"],
+        )?);
+        assert!(stack.is_ignored(&Path::new("/base/a.log"), false));
+        assert!(stack.is_ignored(&Path::new("/base/sub/a.log"), false));
+        assert!(!stack.is_ignored(&Path::new("/base/sub/keep.log"), false));
+        assert!(stack.is_ignored(&Path::new("/base/sub/a.bak"), false));
+        assert!(!stack.is_ignored(&Path::new("/base/a.bak"), false));
+        assert!(stack.is_ignored(&Path::new("/base/sub/generated"), true));
+        assert!(stack.is_ignored(&Path::new("/base/sub/.slugifyignore"), false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_ignore_stack_always_ignores_git_directories() -> Result<()> {
+        let stack = IgnoreStack::new();
+        assert!(stack.is_ignored(&Path::new("/base/.git"), true));
+        assert!(stack.is_ignored(&Path::new("/base/sub/.git"), true));
+        assert!(!stack.is_ignored(&Path::new("/base/.github"), true));
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+use crate::errors::{Error, Result};
+use crate::executor::{Executor, RenameOutcome};
+use crate::filesystem::{Filesystem, Fingerprint, RealFilesystem};
+use crate::plan::{RenameOperation, RenameReason};
+use iocore::Path;
+use serde::{Deserialize, Serialize};
+use std::time::SystemTime;
+
+pub const DEFAULT_JOURNAL_FILENAME: &str = ".slugify-journal.jsonl";
+
+/// `JournalEntry` records a rename performed by `slugify-filenames`
+/// along with enough information about the renamed entry to detect
+/// whether it changed since.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct JournalEntry {
+    pub timestamp: String,
+    pub from: Path,
+    pub to: Path,
+    pub is_dir: bool,
+    /// size of the renamed file, `None` for directories
+    pub size: Option<u64>,
+    /// modification time of the renamed file, `None` for directories
+    /// since their modification time changes as their children get
+    /// renamed
+    pub modified: Option<String>,
+}
+impl JournalEntry {
+    /// `new` records the rename of `from` into `to` on `filesystem`
+    /// after it happened
+    pub fn new<F: Filesystem>(filesystem: &F, from: &Path, to: &Path) -> JournalEntry {
+        let fingerprint = filesystem.fingerprint(to).unwrap_or_default();
+        JournalEntry {
+            timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+            from: from.clone(),
+            to: to.clone(),
+            is_dir: fingerprint.is_dir,
+            size: fingerprint.size,
+            modified: fingerprint.modified,
+        }
+    }
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint {
+            is_dir: self.is_dir,
+            size: self.size,
+            modified: self.modified.clone(),
+        }
+    }
+    /// `check_undo` fails when undoing this entry on `filesystem` could
+    /// lose data: `to` is gone or changed, or `from` has been taken
+    pub fn check_undo<F: Filesystem>(&self, filesystem: &F) -> Result<()> {
+        let refuse = |reason: &str| {
+            Err(Error::UndoRefused {
+                from: self.from.clone(),
+                to: self.to.clone(),
+                reason: reason.to_string(),
+            })
+        };
+        if !filesystem.exists(&self.to) {
+            return refuse("target no longer exists");
+        }
+        if filesystem.exists(&self.from) && !filesystem.is_same_entry(&self.from, &self.to) {
+            return refuse("source path has been taken");
+        }
+        if filesystem.fingerprint(&self.to).ok() != Some(self.fingerprint()) {
+            return refuse("target has changed since it was renamed");
+        }
+        Ok(())
+    }
+    /// `undo_operation` is the [`RenameOperation`] renaming `to` back
+    /// into `from`
+    pub fn undo_operation(&self) -> RenameOperation {
+        RenameOperation {
+            from: self.to.clone(),
+            to: self.from.clone(),
+            original: self.to.clone(),
+            is_dir: self.is_dir,
+            depth: 0,
+            reason: RenameReason::Slugified,
+            conflict: None,
+        }
+    }
+}
+
+/// `Journal` is a JSON Lines file of [`JournalEntry`], one per rename,
+/// appended to as renames happen so that interrupted runs can be
+/// undone as well.
+#[derive(Clone, Debug)]
+pub struct Journal<F: Filesystem = RealFilesystem> {
+    filesystem: F,
+    path: Path,
+}
+impl Journal {
+    pub fn new(path: &Path) -> Journal {
+        Journal::with_filesystem(RealFilesystem, &path.try_absolute())
+    }
+}
+impl<F: Filesystem> Journal<F> {
+    /// `with_filesystem` keeps the journal at `path` on `filesystem`
+    pub fn with_filesystem(filesystem: F, path: &Path) -> Journal<F> {
+        Journal {
+            filesystem,
+            path: path.clone(),
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    pub fn exists(&self) -> bool {
+        self.filesystem.is_file(&self.path)
+    }
+    pub fn append(&self, entry: &JournalEntry) -> Result<()> {
+        let line = format!("{}\n", serde_json::to_string(entry)?);
+        self.filesystem.append(&self.path, line.as_bytes())
+    }
+    pub fn entries(&self) -> Result<Vec<JournalEntry>> {
+        String::from_utf8_lossy(&self.filesystem.read(&self.path)?)
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Ok(serde_json::from_str::<JournalEntry>(line)?))
+            .collect()
+    }
+    /// `rewrite` replaces the contents of the journal with `entries`,
+    /// deleting it when there are none left
+    pub fn rewrite(&self, entries: &[JournalEntry]) -> Result<()> {
+        if entries.is_empty() {
+            if self.exists() {
+                self.filesystem.remove_file(&self.path)?;
+            }
+            return Ok(());
+        }
+        let mut contents = String::new();
+        for entry in entries {
+            contents.push_str(&serde_json::to_string(entry)?);
+            contents.push('\n');
+        }
+        self.filesystem.write(&self.path, contents.as_bytes())
+    }
+    /// `undo` replays the journal in reverse order renaming each `to`
+    /// back into `from`, entries refused by
+    /// [`JournalEntry::check_undo`] or failing to be renamed are kept
+    /// in the journal while the others are removed from it unless
+    /// `executor` is a dry-run.
+    pub fn undo<G: Filesystem>(
+        &self,
+        executor: &Executor<G>,
+    ) -> Result<Vec<(JournalEntry, Result<RenameOutcome>)>> {
+        let mut results = Vec::new();
+        let mut remaining = Vec::new();
+        for entry in self.entries()?.into_iter().rev() {
+            let result = entry
+                .check_undo(executor.filesystem())
+                .and_then(|_| executor.rename(&entry.to, &entry.from));
+            if !matches!(result, Ok(RenameOutcome::Renamed(_))) {
+                remaining.insert(0, entry.clone());
+            }
+            results.push((entry, result));
+        }
+        if !executor.is_dry_run() {
+            self.rewrite(&remaining)?;
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod journal_tests {
+    use crate::{
+        Error, Executor, Filesystem, Journal, JournalEntry, MemoryFilesystem, RenameOutcome, Result,
+    };
+    use iocore::Path;
+
+    #[test]
+    fn test_journal_undo_in_reverse_order() -> Result<()> {
+        let filesystem = MemoryFilesystem::new();
+        let executor = Executor::with_filesystem(&filesystem);
+        let root = Path::new("/virtual");
+        let journal = Journal::with_filesystem(&filesystem, &root.join("journal.jsonl"));
+
+        filesystem.write(&root.join("Some Dir/A File"), b"contents");
+        for (from, to) in [
+            ("Some Dir", "Some-Dir"),
+            ("Some-Dir/A File", "Some-Dir/A-File"),
+        ] {
+            let (from, to) = (root.join(from), root.join(to));
+            executor.rename(&from, &to)?;
+            journal.append(&JournalEntry::new(executor.filesystem(), &from, &to))?;
+        }
+        assert_eq!(journal.entries()?.len(), 2);
+        assert!(filesystem.exists(&root.join("Some-Dir/A-File")));
+
+        let results = journal.undo(&executor)?;
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[0].1.clone()?,
+            RenameOutcome::Renamed(root.join("Some-Dir/A File"))
+        );
+        assert!(filesystem.exists(&root.join("Some Dir/A File")));
+        assert!(!filesystem.exists(&root.join("Some-Dir")));
+        assert!(!journal.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_journal_refuses_to_undo_changed_targets() -> Result<()> {
+        let filesystem = MemoryFilesystem::new();
+        let executor = Executor::with_filesystem(&filesystem);
+        let journal = Journal::with_filesystem(&filesystem, &Path::new("/virtual/journal.jsonl"));
+
+        let (from, to) = (Path::new("/virtual/A File"), Path::new("/virtual/A-File"));
+        filesystem.write(&from, b"contents");
+        executor.rename(&from, &to)?;
+        journal.append(&JournalEntry::new(executor.filesystem(), &from, &to))?;
+        filesystem.write(&to, b"changed contents");
+
+        let results = journal.undo(&executor)?;
+        assert!(results[0].1.is_err());
+        assert!(filesystem.exists(&to));
+        assert_eq!(journal.entries()?.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_undo_looks_entries_up_in_the_filesystem() -> Result<()> {
+        let filesystem = MemoryFilesystem::new();
+        let executor = Executor::with_filesystem(&filesystem);
+        let (from, to) = (Path::new("/virtual/A File"), Path::new("/virtual/A-File"));
+        filesystem.write(&from, b"contents");
+        executor.rename(&from, &to)?;
+        let entry = JournalEntry::new(executor.filesystem(), &from, &to);
+        assert_eq!(entry.size, Some(8));
+        entry.check_undo(executor.filesystem())?;
+
+        // same size, written again
+        filesystem.write(&to, b"CONTENTS");
+        assert!(matches!(
+            entry.check_undo(executor.filesystem()),
+            Err(Error::UndoRefused { .. })
+        ));
+        filesystem.write(&from, b"taken");
+        let error = entry.check_undo(executor.filesystem()).unwrap_err();
+        assert!(error.to_string().contains("source path has been taken"));
+        Ok(())
+    }
+}
@@ -1,10 +1,50 @@
 pub mod cli;
-pub use cli::{heck_aliases, SlugifyFilenames, SlugifyParameters, SlugifyString};
+pub use cli::{SlugifyFilenames, SlugifyParameters, SlugifyString, heck_aliases};
 pub mod errors;
-pub use errors::{Error, Result};
+pub use errors::{
+    EXIT_CHECK_FAILED, EXIT_CONFIG, EXIT_CONFLICT, EXIT_FAILURE, EXIT_IO, EXIT_PARTIAL_FAILURE,
+    EXIT_SUCCESS, EXIT_USAGE, Error, Result,
+};
 
 pub(crate) mod string;
 pub use string::{
-    list_of_trimmed_strings, slugify_string, DEFAULT_SEPARATOR, SPECIAL_PATTERN_CHARS,
-    STRING_REGEX, UNNEEDED_UNIQUEFY_REGEX,
+    DEFAULT_SEPARATOR, SPECIAL_PATTERN_CHARS, STRING_REGEX, UNNEEDED_UNIQUEFY_REGEX,
+    list_of_trimmed_strings, slugify_string, slugify_string_with_separator,
+};
+
+pub(crate) mod slugifier;
+pub use slugifier::{
+    Case, DEFAULT_ALLOWED_CHARS, DEFAULT_COMPOUND_EXTENSIONS, ExtensionHandling, PATH_UNSAFE_CHARS,
+    SlugOptions, Slugifier, WINDOWS_RESERVED_NAMES, is_windows_reserved_name,
+};
+
+pub mod config;
+pub use config::{CONFIG_FILENAME, Config, project_config_path, user_config_path};
+pub mod preset;
+pub use preset::{BUILTIN_PRESETS, Preset};
+pub mod ignore;
+pub use ignore::{
+    GIT_DIRECTORY_NAME, GIT_IGNORE_FILENAME, IGNORE_FILENAME, IgnorePattern, IgnoreRules,
+    IgnoreStack, SLUGIFY_IGNORE_FILENAME, git_work_tree, global_git_excludes_path,
+    is_always_ignored,
+};
+
+pub mod plan;
+pub use plan::{Conflict, ConflictStrategy, RenameOperation, RenamePlan, RenameReason};
+pub mod planner;
+pub use planner::{DEFAULT_SUFFIX_FORMAT, OccupiedNames, Planner, content_hash};
+pub mod filesystem;
+pub use filesystem::{
+    Filesystem, Fingerprint, MemoryFilesystem, RealFilesystem, is_case_insensitive,
+    is_case_only_rename, is_same_entry,
 };
+pub mod git;
+pub use git::{GitFilesystem, has_uncommitted_changes, is_tracked};
+pub mod executor;
+pub use executor::{Executor, RenameOutcome};
+pub mod references;
+pub use references::{ReferenceRewriter, diff, percent_decode, percent_encode};
+pub mod journal;
+pub use journal::{DEFAULT_JOURNAL_FILENAME, Journal, JournalEntry};
+pub mod report;
+pub use report::{OutputFormat, RenameAction, RenameRecord};
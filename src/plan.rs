@@ -0,0 +1,174 @@
+use clap::ValueEnum;
+use iocore::Path;
+use serde::{Deserialize, Serialize};
+
+/// `RenameReason` tells why the [`crate::Planner`] decided on the
+/// target of a [`RenameOperation`]
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenameReason {
+    /// the name is not a slug and its slugified version is available
+    Slugified,
+    /// the slugified name is taken so a counter was appended to it
+    Uniquified,
+    /// the name is already a slug
+    AlreadySlugified,
+    /// the entry is less than `--min-depth` levels below a given path
+    AboveMinDepth,
+    /// the entry matches an ignore pattern, its children are not planned
+    Ignored,
+    /// the slugified name is taken so a short hash of the contents of
+    /// the entry was appended to it
+    Hashed,
+    /// the slugified name is an existing directory which the entry is
+    /// merged into, see [`ConflictStrategy::Merge`]
+    MergedIntoExistingDirectory,
+    /// the slugified name is taken and replaced by the entry, see
+    /// [`ConflictStrategy::Overwrite`]
+    Overwritten,
+    /// the slugified name is taken so the entry is left where it is,
+    /// see [`ConflictStrategy::Skip`]
+    ConflictSkipped,
+    /// the name is made only of characters that slugify to nothing,
+    /// e.g.: `"!!!"`, so the entry is left where it is
+    EmptySlug,
+}
+
+/// `ConflictStrategy` decides what happens to an entry whose slugified
+/// name is taken, both when planning and when executing
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ConflictStrategy {
+    /// append a counter to the slugified name, see --suffix-format
+    #[default]
+    Suffix,
+    /// leave the entry untouched
+    Skip,
+    /// fail before renaming anything, or leave the entry untouched and
+    /// report it as a failure when planning keeps going
+    Error,
+    /// replace the existing entry, merging directories into each other,
+    /// failing when one is a file and the other a directory
+    Overwrite,
+    /// merge directories into existing directories failing when files
+    /// clash, other entries get a suffix
+    Merge,
+    /// append a short hash of the contents of the entry to the
+    /// slugified name
+    Hash,
+}
+
+/// `Conflict` annotates a [`RenameOperation`] whose slugified name
+/// clashes with something else
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum Conflict {
+    /// `target` already existed when planning
+    TargetExists { target: Path },
+    /// every one of `sources`, sorted by name, slugifies to `target`
+    /// and the first one gets it unless it already existed
+    Collision { target: Path, sources: Vec<Path> },
+}
+
+/// `RenameOperation` is a single entry of a [`RenamePlan`]
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RenameOperation {
+    /// where the entry will be once its parent directories have been
+    /// renamed by the preceding operations of the plan
+    pub from: Path,
+    /// equals `from` when the entry is left untouched
+    pub to: Path,
+    /// where the entry is in the filesystem at planning time
+    pub original: Path,
+    pub is_dir: bool,
+    /// 0 for the paths given to the planner, increasing by one for
+    /// each level below them
+    pub depth: usize,
+    pub reason: RenameReason,
+    pub conflict: Option<Conflict>,
+}
+impl RenameOperation {
+    /// `is_rename` is true when performing the operation moves the entry
+    pub fn is_rename(&self) -> bool {
+        self.from.to_string() != self.to.to_string()
+    }
+}
+
+/// `RenamePlan` is the ordered list of [`RenameOperation`] produced by
+/// a [`crate::Planner`] and carried out by an [`crate::Executor`],
+/// parent directories always come before their children.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct RenamePlan {
+    operations: Vec<RenameOperation>,
+}
+impl RenamePlan {
+    pub fn new() -> RenamePlan {
+        RenamePlan::default()
+    }
+    pub fn push(&mut self, operation: RenameOperation) {
+        self.operations.push(operation);
+    }
+    pub fn extend(&mut self, plan: RenamePlan) {
+        self.operations.extend(plan.operations);
+    }
+    pub fn operations(&self) -> &[RenameOperation] {
+        &self.operations
+    }
+    pub fn iter(&self) -> std::slice::Iter<'_, RenameOperation> {
+        self.operations.iter()
+    }
+    pub fn len(&self) -> usize {
+        self.operations.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.operations.is_empty()
+    }
+    /// `renames` only yields the operations that move an entry
+    pub fn renames(&self) -> impl Iterator<Item = &RenameOperation> {
+        self.operations
+            .iter()
+            .filter(|operation| operation.is_rename())
+    }
+    pub fn conflicts(&self) -> impl Iterator<Item = &RenameOperation> {
+        self.operations
+            .iter()
+            .filter(|operation| operation.conflict.is_some())
+    }
+    /// `filter` keeps the operations for which `predicate` returns
+    /// true, note that dropping the rename of a directory invalidates
+    /// the `from` of the operations below it
+    pub fn filter<F: FnMut(&RenameOperation) -> bool>(&self, mut predicate: F) -> RenamePlan {
+        RenamePlan {
+            operations: self
+                .operations
+                .iter()
+                .filter(|operation| predicate(operation))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+impl IntoIterator for RenamePlan {
+    type Item = RenameOperation;
+    type IntoIter = std::vec::IntoIter<RenameOperation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.operations.into_iter()
+    }
+}
+impl<'a> IntoIterator for &'a RenamePlan {
+    type Item = &'a RenameOperation;
+    type IntoIter = std::slice::Iter<'a, RenameOperation>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.operations.iter()
+    }
+}
+impl FromIterator<RenameOperation> for RenamePlan {
+    fn from_iter<T: IntoIterator<Item = RenameOperation>>(iter: T) -> RenamePlan {
+        RenamePlan {
+            operations: iter.into_iter().collect(),
+        }
+    }
+}
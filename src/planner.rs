@@ -0,0 +1,930 @@
+use crate::errors::{Error, Result};
+use crate::filesystem::{Filesystem, RealFilesystem};
+use crate::ignore::{IgnoreStack, is_always_ignored};
+use crate::plan::{Conflict, ConflictStrategy, RenameOperation, RenamePlan, RenameReason};
+use crate::slugifier::Slugifier;
+use iocore::Path;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+
+/// `DEFAULT_SUFFIX_FORMAT` appends `.0`, `.1`... to taken names
+pub const DEFAULT_SUFFIX_FORMAT: &str = ".{n}";
+
+/// `Planner` walks paths without touching the filesystem and produces
+/// the [`RenamePlan`] that slugifies them, looking the paths up in a
+/// [`Filesystem`].
+#[derive(Clone, Debug)]
+pub struct Planner<F: Filesystem = RealFilesystem> {
+    filesystem: F,
+    slugifier: Slugifier,
+    recursive: bool,
+    min_depth: usize,
+    max_depth: Option<usize>,
+    force: bool,
+    excluded: Vec<Path>,
+    on_conflict: ConflictStrategy,
+    suffix_format: String,
+    suffix_start: usize,
+    case_insensitive: Option<bool>,
+}
+impl Planner {
+    pub fn new(slugifier: Slugifier) -> Planner {
+        Planner::with_filesystem(RealFilesystem, slugifier)
+    }
+}
+impl<F: Filesystem> Planner<F> {
+    pub fn with_filesystem(filesystem: F, slugifier: Slugifier) -> Planner<F> {
+        Planner {
+            filesystem,
+            slugifier,
+            recursive: false,
+            min_depth: 0,
+            max_depth: None,
+            force: false,
+            excluded: Vec::new(),
+            on_conflict: ConflictStrategy::default(),
+            suffix_format: DEFAULT_SUFFIX_FORMAT.to_string(),
+            suffix_start: 0,
+            case_insensitive: None,
+        }
+    }
+    pub fn recursive(mut self, recursive: bool) -> Planner<F> {
+        self.recursive = recursive;
+        self
+    }
+    pub fn min_depth(mut self, min_depth: usize) -> Planner<F> {
+        self.min_depth = min_depth;
+        self
+    }
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> Planner<F> {
+        self.max_depth = max_depth;
+        self
+    }
+    /// `force` bypasses ignore rules
+    pub fn force(mut self, force: bool) -> Planner<F> {
+        self.force = force;
+        self
+    }
+    /// `exclude` never plans `path` regardless of [`Planner::force`]
+    pub fn exclude(mut self, path: &Path) -> Planner<F> {
+        self.excluded.push(path.try_absolute());
+        self
+    }
+    pub fn on_conflict(mut self, on_conflict: ConflictStrategy) -> Planner<F> {
+        self.on_conflict = on_conflict;
+        self
+    }
+    /// `suffix_format` is appended to the stem of taken names with
+    /// `{n}` replaced by a counter, e.g.: `"-{n}"` or `"_({n})"`
+    pub fn suffix_format<T: Display>(mut self, suffix_format: T) -> Planner<F> {
+        self.suffix_format = suffix_format.to_string();
+        self
+    }
+    /// `suffix_start` is the first value of the counter of
+    /// [`Planner::suffix_format`]
+    pub fn suffix_start(mut self, suffix_start: usize) -> Planner<F> {
+        self.suffix_start = suffix_start;
+        self
+    }
+    /// `case_insensitive` overrides the probing of each directory with
+    /// [`Filesystem::is_case_insensitive`], `None` probes
+    pub fn case_insensitive(mut self, case_insensitive: Option<bool>) -> Planner<F> {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+    pub fn is_case_insensitive(&self, directory: &Path) -> bool {
+        self.case_insensitive
+            .unwrap_or_else(|| self.filesystem.is_case_insensitive(directory))
+    }
+    /// `occupied_names` lists the names of the entries inside `directory`
+    pub fn occupied_names(&self, directory: &Path) -> OccupiedNames {
+        let mut names = OccupiedNames::new(self.is_case_insensitive(directory));
+        names.extend(entry_names(&self.filesystem, directory));
+        names
+    }
+    pub fn slugifier(&self) -> &Slugifier {
+        &self.slugifier
+    }
+    /// `should_descend` tells whether the children of an entry at
+    /// `depth` are within reach
+    pub fn should_descend(&self, depth: usize) -> bool {
+        self.recursive
+            && match self.max_depth {
+                Some(max_depth) => depth < max_depth,
+                None => true,
+            }
+    }
+    /// `should_ignore` is always false with [`Planner::force`] except
+    /// for `.git` directories and [`Planner::exclude`]d paths which are
+    /// never touched
+    pub fn should_ignore(&self, ignores: &IgnoreStack, path: &Path, is_dir: bool) -> bool {
+        if is_always_ignored(path) || self.excluded.contains(&path.try_absolute()) {
+            return true;
+        }
+        !self.force && ignores.is_ignored(path, is_dir)
+    }
+    /// `plan` returns the operations for every given path, each with
+    /// the ignore rules in effect for it, in the order they must be
+    /// performed
+    ///
+    /// Targets are planned sorted by path so that entries whose
+    /// slugified names collide are resolved in the same order
+    /// regardless of how they were given.
+    pub fn plan(&self, targets: &[(Path, IgnoreStack)]) -> Result<RenamePlan> {
+        let (plan, _) = self.plan_with_state(targets, PlanningState::default())?;
+        Ok(plan)
+    }
+    /// `plan_keep_going` is [`Planner::plan`] except that the targets
+    /// or directories which cannot be read are returned along with
+    /// their error rather than failing the whole plan, the entries
+    /// below them are left out of the plan
+    ///
+    /// Entries whose slugified name is taken under
+    /// [`ConflictStrategy::Error`], or by an entry of another kind under
+    /// [`ConflictStrategy::Overwrite`], are returned as failures too and
+    /// left where they are, the entries below them are still planned.
+    pub fn plan_keep_going(
+        &self,
+        targets: &[(Path, IgnoreStack)],
+    ) -> Result<(RenamePlan, Vec<(Path, Error)>)> {
+        self.plan_with_state(
+            targets,
+            PlanningState {
+                failures: Some(Vec::new()),
+                ..PlanningState::default()
+            },
+        )
+    }
+    fn plan_with_state(
+        &self,
+        targets: &[(Path, IgnoreStack)],
+        mut state: PlanningState,
+    ) -> Result<(RenamePlan, Vec<(Path, Error)>)> {
+        let mut plan = RenamePlan::new();
+        let mut canonical_targets = Vec::<(Path, &IgnoreStack)>::new();
+        for (path, ignores) in targets {
+            match self.filesystem.canonicalize(path) {
+                Ok(path) => canonical_targets.push((path, ignores)),
+                Err(error) => state.fail(path, error)?,
+            }
+        }
+        let mut targets = canonical_targets;
+        targets.sort_by_key(|(path, _)| path.to_string());
+        if self.min_depth == 0 {
+            let mut siblings = BTreeMap::<String, (Path, CollisionGroups)>::new();
+            for (path, _) in &targets {
+                let parent = path.parent().unwrap_or_else(Path::cwd);
+                let (_, groups) = siblings.entry(parent.to_string()).or_insert_with(|| {
+                    let groups = CollisionGroups::new(self.is_case_insensitive(&parent));
+                    (parent, groups)
+                });
+                groups.insert(&self.slugify_name_or_keep(path)?, path);
+            }
+            for (parent, names) in siblings.into_values() {
+                self.register_collisions(&parent, names, &mut state)?;
+            }
+        }
+        for (path, ignores) in targets {
+            self.plan_path(&path, &path, ignores, 0, &mut state, &mut plan)?;
+        }
+        Ok((plan, state.failures.unwrap_or_default()))
+    }
+    /// `register_collisions` records every group with more than one
+    /// source as a [`Conflict::Collision`], logging all of them and
+    /// failing with the first one under [`ConflictStrategy::Error`]
+    /// unless planning keeps going, in which case every source that
+    /// would be renamed is recorded as a failure
+    fn register_collisions(
+        &self,
+        parent: &Path,
+        groups: CollisionGroups,
+        state: &mut PlanningState,
+    ) -> Result<()> {
+        let mut errors = Vec::<(Path, Error)>::new();
+        for (name, sources) in groups.groups.into_values() {
+            if sources.len() < 2 {
+                continue;
+            }
+            let target = parent.join(&name);
+            let error = Error::Collision {
+                target: target.clone(),
+                sources: sources.clone(),
+            };
+            if self.on_conflict == ConflictStrategy::Error {
+                log::error!("{error}");
+                let renamed = sources.iter().filter(|source| source.name() != name);
+                errors.extend(renamed.map(|source| (source.clone(), error.clone())));
+            } else {
+                log::warn!("{error}");
+            }
+            let conflict = Conflict::Collision {
+                target,
+                sources: sources.clone(),
+            };
+            for source in sources {
+                state
+                    .collisions
+                    .insert(source.to_string(), conflict.clone());
+            }
+        }
+        if errors.is_empty() {
+            return Ok(());
+        }
+        match &mut state.failures {
+            Some(failures) => {
+                failures.extend(errors);
+                Ok(())
+            }
+            None => Err(errors.swap_remove(0).1),
+        }
+    }
+    /// `original` is where `path` is in the filesystem before any
+    /// of its parent directories get renamed, ignore patterns are
+    /// matched against it.
+    fn plan_path(
+        &self,
+        path: &Path,
+        original: &Path,
+        ignores: &IgnoreStack,
+        depth: usize,
+        state: &mut PlanningState,
+        plan: &mut RenamePlan,
+    ) -> Result<()> {
+        let is_dir = self.filesystem.is_dir(original);
+        let mut operation = RenameOperation {
+            from: path.clone(),
+            to: path.clone(),
+            original: original.clone(),
+            is_dir,
+            depth,
+            reason: RenameReason::AboveMinDepth,
+            conflict: None,
+        };
+        // entries that collide under `ConflictStrategy::Error` were
+        // already recorded as failures by `register_collisions`
+        let mut failed = self.on_conflict == ConflictStrategy::Error
+            && matches!(
+                state.collisions.get(&original.to_string()),
+                Some(Conflict::Collision { target, .. }) if target.name() != original.name()
+            );
+        // entries the filesystem refuses to rename, such as modified
+        // ones under git, fail before anything gets renamed
+        if depth >= self.min_depth
+            && !failed
+            && self.slugify_name_or_keep(original)? != original.name()
+            && let Err(error) = self.filesystem.check_rename(original)
+        {
+            state.fail(original, error)?;
+            failed = true;
+        }
+        if depth >= self.min_depth && !failed {
+            let parent = original.parent().unwrap_or_else(Path::cwd);
+            let names = state
+                .occupied
+                .entry(parent.to_string())
+                .or_insert_with(|| self.occupied_names(&parent));
+            let slug = self.slugify_name(original)?;
+            let target = parent.join(&slug);
+            let mut merged_names = None;
+            if slug.is_empty() {
+                log::warn!("not renaming {original}: its name slugifies to nothing");
+                operation.reason = RenameReason::EmptySlug;
+            } else if slug == original.name() {
+                operation.reason = RenameReason::AlreadySlugified;
+            } else if !self.is_taken(original, &slug, names) {
+                operation.reason = RenameReason::Slugified;
+                names.remove(&original.name());
+                names.insert(&slug);
+                operation.to = path.with_filename(&slug);
+            } else {
+                operation.conflict = Some(Conflict::TargetExists {
+                    target: target.clone(),
+                });
+                let merges = is_dir && self.filesystem.is_dir(&target);
+                match self.on_conflict {
+                    ConflictStrategy::Skip => {
+                        operation.reason = RenameReason::ConflictSkipped;
+                    }
+                    ConflictStrategy::Error => {
+                        let error = Error::TargetExists {
+                            from: original.clone(),
+                            to: target.clone(),
+                        };
+                        state.fail(original, error)?;
+                        failed = true;
+                    }
+                    ConflictStrategy::Overwrite | ConflictStrategy::Merge if merges => {
+                        operation.reason = RenameReason::MergedIntoExistingDirectory;
+                        names.remove(&original.name());
+                        operation.to = path.with_filename(&slug);
+                        merged_names = Some(entry_names(&self.filesystem, &target));
+                    }
+                    ConflictStrategy::Overwrite
+                        if self.filesystem.exists(&target)
+                            && is_dir != self.filesystem.is_dir(&target) =>
+                    {
+                        // a file cannot replace a directory nor the
+                        // other way around
+                        let error = Error::TargetExists {
+                            from: original.clone(),
+                            to: target.clone(),
+                        };
+                        state.fail(original, error)?;
+                        failed = true;
+                    }
+                    ConflictStrategy::Overwrite if self.filesystem.exists(&target) => {
+                        operation.reason = RenameReason::Overwritten;
+                        names.remove(&original.name());
+                        operation.to = path.with_filename(&slug);
+                    }
+                    ConflictStrategy::Suffix
+                    | ConflictStrategy::Overwrite
+                    | ConflictStrategy::Merge
+                    | ConflictStrategy::Hash => {
+                        let (new_name, reason) = self.unique_new_name(original, names)?;
+                        operation.reason = reason;
+                        names.remove(&original.name());
+                        names.insert(&new_name);
+                        operation.to = path.with_filename(&new_name);
+                    }
+                }
+            }
+            if let Some(merged_names) = merged_names {
+                // the children of a merged directory end up next to the
+                // contents of the directory it is merged into
+                state
+                    .occupied
+                    .entry(original.to_string())
+                    .or_insert_with(|| self.occupied_names(original))
+                    .extend(merged_names);
+            }
+            if let Some(conflict) = state.collisions.get(&original.to_string()) {
+                operation.conflict = Some(conflict.clone());
+            }
+        }
+        let new_path = operation.to.clone();
+        if !failed {
+            plan.push(operation);
+        }
+        if self.should_descend(depth) && is_dir {
+            let listing = ignores
+                .with_directory_on(&self.filesystem, original)
+                .and_then(|ignores| Ok((ignores, self.filesystem.list(original)?)));
+            let (ignores, sub_paths) = match listing {
+                Ok(listing) => listing,
+                Err(error) => return state.fail(original, error),
+            };
+            let mut groups = CollisionGroups::new(
+                state
+                    .occupied
+                    .entry(original.to_string())
+                    .or_insert_with(|| self.occupied_names(original))
+                    .is_case_insensitive(),
+            );
+            let mut ignored = BTreeSet::<String>::new();
+            for sub_path in &sub_paths {
+                let name =
+                    if self.should_ignore(&ignores, sub_path, self.filesystem.is_dir(sub_path)) {
+                        ignored.insert(sub_path.to_string());
+                        sub_path.name()
+                    } else if depth + 1 < self.min_depth {
+                        sub_path.name()
+                    } else {
+                        self.slugify_name_or_keep(sub_path)?
+                    };
+                groups.insert(&name, sub_path);
+            }
+            self.register_collisions(original, groups, state)?;
+            for sub_path in sub_paths {
+                let new_sub_path = new_path.join(sub_path.name());
+                if ignored.contains(&sub_path.to_string()) {
+                    plan.push(RenameOperation {
+                        from: new_sub_path.clone(),
+                        to: new_sub_path,
+                        conflict: state.collisions.get(&sub_path.to_string()).cloned(),
+                        original: sub_path.clone(),
+                        is_dir: self.filesystem.is_dir(&sub_path),
+                        depth: depth + 1,
+                        reason: RenameReason::Ignored,
+                    });
+                    continue;
+                }
+                self.plan_path(&new_sub_path, &sub_path, &ignores, depth + 1, state, plan)?;
+            }
+        }
+        Ok(())
+    }
+    /// `slugify_name` slugifies the name of `path`, treating the
+    /// extension of files separately
+    pub fn slugify_name(&self, path: &Path) -> Result<String> {
+        let (name, extension) = if self.filesystem.is_file(path) {
+            self.slugifier.split_extension(&path.name())
+        } else {
+            (path.name(), None)
+        };
+        self.slugifier.slugify_filename(&name, extension.as_deref())
+    }
+    /// `slugify_name_or_keep` is [`Planner::slugify_name`] falling back
+    /// to the current name of `path` when it slugifies to nothing, see
+    /// [`RenameReason::EmptySlug`]
+    fn slugify_name_or_keep(&self, path: &Path) -> Result<String> {
+        let slug = self.slugify_name(path)?;
+        Ok(if slug.is_empty() { path.name() } else { slug })
+    }
+    /// `is_taken` tells whether renaming `path` into `new_filename`
+    /// clashes with any of the `occupied` names in its parent
+    /// directory, an entry never clashes with itself
+    pub fn is_taken(&self, path: &Path, new_filename: &str, occupied: &OccupiedNames) -> bool {
+        occupied.contains(new_filename) && !occupied.is_same_name(&path.name(), new_filename)
+    }
+    /// `unique_new_name` returns the slugified name of `path` that
+    /// does not clash with any of the `occupied` names in its parent
+    /// directory, a hash of the contents of `path` comes before the
+    /// counter of [`Planner::suffix_format`] with
+    /// [`ConflictStrategy::Hash`]
+    pub fn unique_new_name(
+        &self,
+        path: &Path,
+        occupied: &OccupiedNames,
+    ) -> Result<(String, RenameReason)> {
+        let new_filename = self.slugify_name(path)?;
+        let (mut new_name, new_extension) = if self.filesystem.is_file(path)
+            && self.slugifier.split_extension(&path.name()).1.is_some()
+        {
+            self.slugifier.split_extension(&new_filename)
+        } else {
+            (new_filename.clone(), None)
+        };
+
+        if !self.is_taken(path, &new_filename, occupied) {
+            return Ok((new_filename, RenameReason::Slugified));
+        }
+        let mut reason = RenameReason::Uniquified;
+        let mut unique_filename = new_filename.clone();
+        if self.on_conflict == ConflictStrategy::Hash {
+            new_name = format!(
+                "{new_name}{}{}",
+                self.slugifier.options().separator,
+                content_hash(&self.filesystem, path)?
+            );
+            unique_filename = Path::join_extension(&new_name, new_extension.clone());
+            reason = RenameReason::Hashed;
+        }
+        let mut count = self.suffix_start;
+        while self.is_taken(path, &unique_filename, occupied) {
+            let suffix = self.suffix_format.replace("{n}", &count.to_string());
+            unique_filename =
+                Path::join_extension(format!("{new_name}{suffix}"), new_extension.clone());
+            count += 1;
+        }
+        log::info!(
+            "using new name {:#?} since {:#?} already exists",
+            path.with_filename(&unique_filename).to_string(),
+            path.with_filename(&new_filename).to_string()
+        );
+        Ok((unique_filename, reason))
+    }
+    /// `unique_new_path` plans the new path of a single entry
+    /// against the current contents of its parent directory
+    pub fn unique_new_path(&self, path: &Path) -> Result<Path> {
+        let path = self
+            .filesystem
+            .canonicalize(path)
+            .unwrap_or_else(|_| path.clone());
+        let occupied = match path.parent() {
+            Some(parent) => self.occupied_names(&parent),
+            None => OccupiedNames::default(),
+        };
+        Ok(path.with_filename(self.unique_new_name(&path, &occupied)?.0))
+    }
+}
+
+/// `PlanningState` is shared by every call of `Planner::plan_path`
+/// within a single [`Planner::plan`]
+#[derive(Default)]
+struct PlanningState {
+    /// the names each directory will contain as renames get planned,
+    /// keyed by the original path of the directory
+    occupied: BTreeMap<String, OccupiedNames>,
+    /// the [`Conflict::Collision`] of each entry that has one, keyed by
+    /// its original path
+    collisions: BTreeMap<String, Conflict>,
+    /// the paths which could not be planned, `None` unless planning
+    /// keeps going past them, see [`Planner::plan_keep_going`]
+    failures: Option<Vec<(Path, Error)>>,
+}
+impl PlanningState {
+    /// `fail` records the failure to plan `path` when planning keeps
+    /// going and returns `error` otherwise
+    fn fail(&mut self, path: &Path, error: Error) -> Result<()> {
+        match &mut self.failures {
+            Some(failures) => {
+                log::warn!("{error}");
+                failures.push((path.clone(), error));
+                Ok(())
+            }
+            None => Err(error),
+        }
+    }
+}
+
+/// `OccupiedNames` are the names of the entries of a directory,
+/// compared regardless of case when the directory is case-insensitive
+#[derive(Clone, Debug, Default)]
+pub struct OccupiedNames {
+    names: BTreeSet<String>,
+    case_insensitive: bool,
+}
+impl OccupiedNames {
+    pub fn new(case_insensitive: bool) -> OccupiedNames {
+        OccupiedNames {
+            names: BTreeSet::new(),
+            case_insensitive,
+        }
+    }
+    pub fn is_case_insensitive(&self) -> bool {
+        self.case_insensitive
+    }
+    fn key(&self, name: &str) -> String {
+        if self.case_insensitive {
+            name.to_lowercase()
+        } else {
+            name.to_string()
+        }
+    }
+    /// `is_same_name` tells whether `a` and `b` name the same entry
+    pub fn is_same_name(&self, a: &str, b: &str) -> bool {
+        self.key(a) == self.key(b)
+    }
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(&self.key(name))
+    }
+    pub fn insert(&mut self, name: &str) {
+        self.names.insert(self.key(name));
+    }
+    pub fn remove(&mut self, name: &str) {
+        self.names.remove(&self.key(name));
+    }
+    pub fn extend(&mut self, names: impl IntoIterator<Item = String>) {
+        for name in names {
+            self.insert(&name);
+        }
+    }
+}
+
+/// `CollisionGroups` gathers the entries of a directory by the name
+/// they are about to get
+struct CollisionGroups {
+    occupied: OccupiedNames,
+    /// the name of the first entry inserted in each group followed by
+    /// every entry of the group, keyed by [`OccupiedNames::key`]
+    groups: BTreeMap<String, (String, Vec<Path>)>,
+}
+impl CollisionGroups {
+    fn new(case_insensitive: bool) -> CollisionGroups {
+        CollisionGroups {
+            occupied: OccupiedNames::new(case_insensitive),
+            groups: BTreeMap::new(),
+        }
+    }
+    fn insert(&mut self, name: &str, path: &Path) {
+        self.groups
+            .entry(self.occupied.key(name))
+            .or_insert_with(|| (name.to_string(), Vec::new()))
+            .1
+            .push(path.clone());
+    }
+}
+
+/// `content_hash` is the first 8 hexadecimal digits of the 64-bit
+/// FNV-1a hash of the contents of a file or of the names inside a
+/// directory
+pub fn content_hash<F: Filesystem>(filesystem: &F, path: &Path) -> Result<String> {
+    let bytes = if filesystem.is_dir(path) {
+        entry_names(filesystem, path)
+            .into_iter()
+            .collect::<Vec<String>>()
+            .join("\n")
+            .into_bytes()
+    } else {
+        filesystem.read(path)?
+    };
+    let hash = bytes.iter().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    });
+    Ok(format!("{hash:016x}")[..8].to_string())
+}
+
+/// `entry_names` lists the names of the entries inside `directory`
+fn entry_names<F: Filesystem>(filesystem: &F, directory: &Path) -> BTreeSet<String> {
+    filesystem
+        .list(directory)
+        .unwrap_or_default()
+        .iter()
+        .map(|path| path.name())
+        .collect()
+}
+
+#[cfg(test)]
+mod planner_tests {
+    use crate::{
+        Case, Conflict, ConflictStrategy, Error, Filesystem, IgnoreRules, IgnoreStack,
+        MemoryFilesystem, Planner, RenamePlan, RenameReason, Result, SlugOptions,
+    };
+    use iocore::Path;
+
+    #[test]
+    fn test_plan_records_reasons_and_conflicts() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem = MemoryFilesystem::new()
+            .with_files(&root, ["Dir/A B", "Dir/A-B", "Dir/skip me", "Dir/ok"]);
+        let ignores = IgnoreStack::from_rules(IgnoreRules::from_lines(&root, ["skip*"])?);
+        let planner =
+            Planner::with_filesystem(&filesystem, SlugOptions::new().build()?).recursive(true);
+        let plan = planner.plan(&[(root.join("Dir"), ignores)])?;
+
+        let summary = plan
+            .iter()
+            .map(|operation| (operation.to.name(), operation.reason))
+            .collect::<Vec<(String, RenameReason)>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("Dir".to_string(), RenameReason::AlreadySlugified),
+                ("A-B.0".to_string(), RenameReason::Uniquified),
+                ("A-B".to_string(), RenameReason::AlreadySlugified),
+                ("ok".to_string(), RenameReason::AlreadySlugified),
+                ("skip me".to_string(), RenameReason::Ignored),
+            ]
+        );
+        assert_eq!(plan.renames().count(), 1);
+        assert_eq!(
+            plan.conflicts()
+                .map(|operation| operation.conflict.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                Some(Conflict::Collision {
+                    target: root.join("Dir/A-B"),
+                    sources: vec![root.join("Dir/A B"), root.join("Dir/A-B")]
+                });
+                2
+            ]
+        );
+        assert!(filesystem.exists(&root.join("Dir/A B")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_can_be_filtered() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem = MemoryFilesystem::new().with_files(&root, ["One Two", "Three Four"]);
+        let planner = Planner::with_filesystem(&filesystem, SlugOptions::new().build()?);
+        let plan = planner.plan(&[
+            (root.join("One Two"), IgnoreStack::new()),
+            (root.join("Three Four"), IgnoreStack::new()),
+        ])?;
+        let plan = plan.filter(|operation| operation.original.name().starts_with("One"));
+        assert_eq!(plan.len(), 1);
+        assert_eq!(plan.operations()[0].to, root.join("One-Two"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_on_conflict_strategies() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem = MemoryFilesystem::new().with_files(&root, ["A B", "A-B"]);
+        let plan = |planner: Planner<&MemoryFilesystem>| {
+            planner.plan(&[(root.join("A B"), IgnoreStack::new())])
+        };
+        let planner = Planner::with_filesystem(&filesystem, SlugOptions::new().build()?);
+        for (on_conflict, name, reason) in [
+            (ConflictStrategy::Suffix, "A-B.0", RenameReason::Uniquified),
+            (ConflictStrategy::Skip, "A B", RenameReason::ConflictSkipped),
+            (
+                ConflictStrategy::Overwrite,
+                "A-B",
+                RenameReason::Overwritten,
+            ),
+            (ConflictStrategy::Merge, "A-B.0", RenameReason::Uniquified),
+            (ConflictStrategy::Hash, "A-B-cbf29ce4", RenameReason::Hashed),
+        ] {
+            let plan = plan(planner.clone().on_conflict(on_conflict))?;
+            let operation = &plan.operations()[0];
+            assert_eq!(
+                (operation.to.name(), operation.reason),
+                (name.to_string(), reason)
+            );
+            assert_eq!(
+                operation.conflict,
+                Some(Conflict::TargetExists {
+                    target: root.join("A-B")
+                })
+            );
+        }
+        assert!(plan(planner.clone().on_conflict(ConflictStrategy::Error)).is_err());
+        let plan = plan(planner.suffix_format("_({n})").suffix_start(1))?;
+        assert_eq!(plan.operations()[0].to.name(), "A-B_(1)");
+        Ok(())
+    }
+
+    #[test]
+    fn test_collisions_are_resolved_in_name_order() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem =
+            MemoryFilesystem::new().with_files(&root, ["Dir/A B", "Dir/A  B", "Dir/a-b"]);
+        let planner =
+            Planner::with_filesystem(&filesystem, SlugOptions::new().case(Case::Lower).build()?)
+                .recursive(true);
+        let plan = planner.plan(&[(root.join("Dir"), IgnoreStack::new())])?;
+        let summary = plan
+            .iter()
+            .skip(1)
+            .map(|operation| (operation.original.name(), operation.to.name()))
+            .collect::<Vec<(String, String)>>();
+        assert_eq!(
+            summary,
+            vec![
+                ("A  B".to_string(), "a-b.0".to_string()),
+                ("A B".to_string(), "a-b.1".to_string()),
+                ("a-b".to_string(), "a-b".to_string()),
+            ]
+        );
+        assert_eq!(plan.conflicts().count(), 3);
+
+        let error = planner
+            .on_conflict(ConflictStrategy::Error)
+            .plan(&[(root.join("Dir"), IgnoreStack::new())])
+            .unwrap_err();
+        assert!(matches!(&error, Error::Collision { sources, .. } if sources.len() == 3));
+        assert!(error.to_string().contains(r#"3 entries slugify to"#));
+        assert!(error.to_string().contains(r#""A  B", "A B", "a-b""#));
+        Ok(())
+    }
+
+    #[test]
+    fn test_case_insensitive_directories() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem = MemoryFilesystem::new().with_files(&root, ["Foo Bar", "FOO-BAR", "Baz"]);
+        let targets = [
+            (root.join("Foo Bar"), IgnoreStack::new()),
+            (root.join("Baz"), IgnoreStack::new()),
+        ];
+        let planner =
+            Planner::with_filesystem(&filesystem, SlugOptions::new().case(Case::Lower).build()?);
+        let names = |plan: RenamePlan| {
+            plan.iter()
+                .map(|operation| (operation.to.name(), operation.reason))
+                .collect::<Vec<(String, RenameReason)>>()
+        };
+        assert_eq!(
+            names(
+                planner
+                    .clone()
+                    .case_insensitive(Some(false))
+                    .plan(&targets)?
+            ),
+            vec![
+                ("baz".to_string(), RenameReason::Slugified),
+                ("foo-bar".to_string(), RenameReason::Slugified),
+            ]
+        );
+        assert_eq!(
+            names(planner.case_insensitive(Some(true)).plan(&targets)?),
+            vec![
+                ("baz".to_string(), RenameReason::Slugified),
+                ("foo-bar.0".to_string(), RenameReason::Uniquified),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_uniquify_before_compound_extensions() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem =
+            MemoryFilesystem::new().with_files(&root, ["My Archive.tar.gz", "My-Archive.tar.gz"]);
+        let planner = Planner::with_filesystem(&filesystem, SlugOptions::new().build()?);
+        let plan = planner.plan(&[(root.join("My Archive.tar.gz"), IgnoreStack::new())])?;
+        assert_eq!(plan.operations()[0].to, root.join("My-Archive.0.tar.gz"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_names_made_only_of_punctuation_are_left_in_place() -> Result<()> {
+        let filesystem = MemoryFilesystem::new();
+        filesystem.write(&Path::new("/virtual/Dir/!!!/A B.txt"), &[]);
+        let planner =
+            Planner::with_filesystem(&filesystem, SlugOptions::new().build()?).recursive(true);
+        let plan = planner.plan(&[(Path::new("/virtual/Dir"), IgnoreStack::new())])?;
+        let summary = plan
+            .iter()
+            .map(|operation| (operation.original.to_string(), operation.to.to_string()))
+            .collect::<Vec<(String, String)>>();
+        assert!(summary.contains(&(
+            "/virtual/Dir/!!!/A B.txt".to_string(),
+            "/virtual/Dir/!!!/A-B.txt".to_string()
+        )));
+        let operation = plan
+            .iter()
+            .find(|operation| operation.original.name() == "!!!")
+            .expect("!!!");
+        assert_eq!(operation.reason, RenameReason::EmptySlug);
+        assert_eq!(operation.to, operation.original);
+        assert!(plan.conflicts().next().is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_keep_going_records_conflicts_under_error_strategy() -> Result<()> {
+        let filesystem = MemoryFilesystem::new();
+        filesystem.write(&Path::new("/virtual/Top Dir/C D.txt"), &[]);
+        filesystem.create_dir(&Path::new("/virtual/Top-Dir"));
+        filesystem.write(&Path::new("/virtual/Sub/A B.txt"), &[]);
+        filesystem.write(&Path::new("/virtual/Sub/A  B.txt"), &[]);
+        filesystem.write(&Path::new("/virtual/Sub/E F.txt"), &[]);
+        let planner = Planner::with_filesystem(&filesystem, SlugOptions::new().build()?)
+            .recursive(true)
+            .on_conflict(ConflictStrategy::Error);
+        let targets = [
+            (Path::new("/virtual/Top Dir"), IgnoreStack::new()),
+            (Path::new("/virtual/Sub"), IgnoreStack::new()),
+        ];
+        assert!(matches!(
+            planner.plan(&targets),
+            Err(Error::Collision { .. })
+        ));
+
+        let (plan, failures) = planner.plan_keep_going(&targets)?;
+        let renames = plan
+            .renames()
+            .map(|operation| operation.to.to_string())
+            .collect::<Vec<String>>();
+        assert_eq!(
+            renames,
+            vec!["/virtual/Sub/E-F.txt", "/virtual/Top Dir/C-D.txt"]
+        );
+        let failures = failures
+            .iter()
+            .map(|(path, error)| (path.name(), error.clone()))
+            .collect::<Vec<(String, Error)>>();
+        assert_eq!(failures.len(), 3);
+        assert!(matches!(&failures[0], (name, Error::Collision { .. }) if name == "A  B.txt"));
+        assert!(matches!(&failures[1], (name, Error::Collision { .. }) if name == "A B.txt"));
+        assert!(matches!(&failures[2], (name, Error::TargetExists { .. }) if name == "Top Dir"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_overwrite_never_replaces_entries_of_another_kind() -> Result<()> {
+        let root = Path::new("/virtual");
+        let filesystem = MemoryFilesystem::new().with_files(&root, ["A B", "C-D/e.txt", "F G"]);
+        filesystem.create_dir(&root.join("A-B"));
+        filesystem.create_dir(&root.join("C D"));
+        let planner = Planner::with_filesystem(&filesystem, SlugOptions::new().build()?)
+            .on_conflict(ConflictStrategy::Overwrite);
+        let targets = [
+            (root.join("A B"), IgnoreStack::new()),
+            (root.join("C D"), IgnoreStack::new()),
+            (root.join("F G"), IgnoreStack::new()),
+        ];
+        assert!(matches!(
+            planner.plan(&targets),
+            Err(Error::TargetExists { from, .. }) if from.name() == "A B"
+        ));
+
+        let (plan, failures) = planner.plan_keep_going(&targets)?;
+        assert_eq!(
+            plan.renames()
+                .map(|operation| (operation.to.name(), operation.reason))
+                .collect::<Vec<(String, RenameReason)>>(),
+            vec![
+                ("C-D".to_string(), RenameReason::MergedIntoExistingDirectory),
+                ("F-G".to_string(), RenameReason::Slugified),
+            ]
+        );
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, root.join("A B"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_keep_going_returns_failures() -> Result<()> {
+        let filesystem = MemoryFilesystem::new();
+        filesystem.write(&Path::new("/virtual/A B.txt"), &[]);
+        let planner = Planner::with_filesystem(&filesystem, SlugOptions::new().build()?);
+        let targets = [
+            (Path::new("/virtual/Missing"), IgnoreStack::new()),
+            (Path::new("/virtual/A B.txt"), IgnoreStack::new()),
+        ];
+        assert!(matches!(
+            planner.plan(&targets),
+            Err(Error::NotFound { .. })
+        ));
+
+        let (plan, failures) = planner.plan_keep_going(&targets)?;
+        assert_eq!(plan.renames().count(), 1);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0.to_string(), "/virtual/Missing");
+        Ok(())
+    }
+}
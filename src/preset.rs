@@ -0,0 +1,230 @@
+use crate::slugifier::{Case, ExtensionHandling, SlugOptions};
+use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+/// names of the presets built into [`Preset::builtin`]
+pub const BUILTIN_PRESETS: &[&str] = &[
+    "web",
+    "python",
+    "posix-portable",
+    "windows-safe",
+    "s3-key",
+    "ascii-strict",
+];
+
+/// `Preset` bundles a slug style under a name, either built in, see
+/// [`BUILTIN_PRESETS`], or defined as a profile in a configuration
+/// file, e.g.:
+///
+/// ```toml
+/// [profiles.dataset]
+/// separator = "_"
+/// case = "preserve"
+/// max-length = 100
+/// extension = "preserve"
+/// ```
+///
+/// Settings left out keep their default values.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Preset {
+    pub separator: Option<String>,
+    pub case: Option<Case>,
+    pub allowed_chars: Option<String>,
+    pub max_length: Option<usize>,
+    pub no_transliterate: Option<bool>,
+    pub extension: Option<ExtensionHandling>,
+    pub avoid_reserved_names: Option<bool>,
+}
+impl Preset {
+    pub fn new() -> Preset {
+        Preset::default()
+    }
+    /// `builtin` returns the preset named `name` out of
+    /// [`BUILTIN_PRESETS`]
+    pub fn builtin(name: &str) -> Option<Preset> {
+        let preset = match name {
+            // kebab-case URLs and asset names
+            "web" => Preset::new()
+                .separator("-")
+                .case(Case::Kebab)
+                .allowed_chars("a-zA-Z0-9-")
+                .extension(ExtensionHandling::Lowercase),
+            // importable module names
+            "python" => Preset::new()
+                .separator("_")
+                .case(Case::Snake)
+                .allowed_chars("a-zA-Z0-9_")
+                .extension(ExtensionHandling::Lowercase),
+            // the POSIX portable filename character set
+            "posix-portable" => Preset::new()
+                .separator("_")
+                .case(Case::Preserve)
+                .allowed_chars("a-zA-Z0-9._-")
+                .max_length(255)
+                .extension(ExtensionHandling::Preserve),
+            // names that survive NTFS and FAT, whose extensions are
+            // case-insensitive anyway
+            "windows-safe" => Preset::new()
+                .separator("_")
+                .case(Case::Preserve)
+                .allowed_chars("a-zA-Z0-9._-")
+                .max_length(255)
+                .extension(ExtensionHandling::Lowercase)
+                .avoid_reserved_names(true),
+            // the characters AWS documents as safe in object keys
+            "s3-key" => Preset::new()
+                .separator("-")
+                .case(Case::Lower)
+                .allowed_chars("a-zA-Z0-9!_.*'()-")
+                .max_length(1024)
+                .extension(ExtensionHandling::Lowercase),
+            // nothing but lowercase letters, digits and separators
+            "ascii-strict" => Preset::new()
+                .separator("-")
+                .case(Case::Lower)
+                .allowed_chars("a-zA-Z0-9")
+                .max_length(64)
+                .extension(ExtensionHandling::Slugify),
+            _ => return None,
+        };
+        Some(preset.no_transliterate(false))
+    }
+    pub fn separator<T: std::fmt::Display>(mut self, separator: T) -> Preset {
+        self.separator = Some(separator.to_string());
+        self
+    }
+    pub fn case(mut self, case: Case) -> Preset {
+        self.case = Some(case);
+        self
+    }
+    pub fn allowed_chars<T: std::fmt::Display>(mut self, allowed_chars: T) -> Preset {
+        self.allowed_chars = Some(allowed_chars.to_string());
+        self
+    }
+    pub fn max_length(mut self, max_length: usize) -> Preset {
+        self.max_length = Some(max_length);
+        self
+    }
+    pub fn no_transliterate(mut self, no_transliterate: bool) -> Preset {
+        self.no_transliterate = Some(no_transliterate);
+        self
+    }
+    pub fn extension(mut self, extension: ExtensionHandling) -> Preset {
+        self.extension = Some(extension);
+        self
+    }
+    pub fn avoid_reserved_names(mut self, avoid_reserved_names: bool) -> Preset {
+        self.avoid_reserved_names = Some(avoid_reserved_names);
+        self
+    }
+    /// `settings` returns the preset as configuration file settings,
+    /// see [`crate::Config`]
+    pub fn settings(&self) -> Table {
+        match Value::try_from(self) {
+            Ok(Value::Table(settings)) => settings,
+            _ => Table::new(),
+        }
+    }
+    /// `apply` overrides the fields of `options` set by this preset
+    pub fn apply(&self, mut options: SlugOptions) -> SlugOptions {
+        if let Some(separator) = &self.separator {
+            options = options.separator(separator);
+        }
+        if let Some(case) = self.case {
+            options = options.case(case);
+        }
+        if let Some(allowed_chars) = &self.allowed_chars {
+            options = options.allowed_chars(allowed_chars);
+        }
+        if let Some(max_length) = self.max_length {
+            options = options.max_length(Some(max_length));
+        }
+        if let Some(no_transliterate) = self.no_transliterate {
+            options = options.transliterate(!no_transliterate);
+        }
+        if let Some(extension) = self.extension {
+            options = options.extension(extension);
+        }
+        if let Some(avoid_reserved_names) = self.avoid_reserved_names {
+            options = options.avoid_reserved_names(avoid_reserved_names);
+        }
+        options
+    }
+}
+
+#[cfg(test)]
+mod preset_tests {
+    use crate::{BUILTIN_PRESETS, Preset, Result, SlugOptions};
+
+    fn slugify_filename(preset: &str, filename: &str) -> Result<String> {
+        let slugifier = Preset::builtin(preset)
+            .expect(preset)
+            .apply(SlugOptions::new())
+            .build()?;
+        let (stem, extension) = slugifier.split_extension(filename);
+        slugifier.slugify_filename(&stem, extension.as_deref())
+    }
+
+    #[test]
+    fn test_builtin_presets() -> Result<()> {
+        let filename = "Données_Brutes v1.2 (final).JPG";
+        assert_eq!(
+            slugify_filename("web", filename)?,
+            "donnees-brutes-v1-2-final.jpg"
+        );
+        assert_eq!(
+            slugify_filename("python", filename)?,
+            "donnees_brutes_v1_2_final.jpg"
+        );
+        assert_eq!(
+            slugify_filename("posix-portable", filename)?,
+            "Donnees_Brutes_v1.2_final.JPG"
+        );
+        assert_eq!(
+            slugify_filename("windows-safe", filename)?,
+            "Donnees_Brutes_v1.2_final.jpg"
+        );
+        assert_eq!(
+            slugify_filename("s3-key", filename)?,
+            "donnees_brutes-v1.2-(final).jpg"
+        );
+        assert_eq!(
+            slugify_filename("ascii-strict", filename)?,
+            "donnees-brutes-v1-2-final.jpg"
+        );
+        assert!(Preset::builtin("no-such-preset").is_none());
+        for name in BUILTIN_PRESETS {
+            assert!(Preset::builtin(name).is_some(), "{name}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_windows_safe_avoids_reserved_names() -> Result<()> {
+        assert_eq!(slugify_filename("windows-safe", "CON.txt")?, "CON_.txt");
+        assert_eq!(slugify_filename("windows-safe", "nul")?, "nul_");
+        assert_eq!(
+            slugify_filename("windows-safe", "Lpt1.tar.gz")?,
+            "Lpt1_.tar.gz"
+        );
+        assert_eq!(slugify_filename("windows-safe", "CON.")?, "CON_");
+        assert_eq!(slugify_filename("windows-safe", "Notes...")?, "Notes");
+        assert_eq!(
+            slugify_filename("windows-safe", "CONSOLE.txt")?,
+            "CONSOLE.txt"
+        );
+        assert_eq!(slugify_filename("posix-portable", "CON.txt")?, "CON.txt");
+        Ok(())
+    }
+
+    #[test]
+    fn test_preset_settings_use_flag_names() {
+        let settings = Preset::builtin("s3-key").unwrap().settings();
+        assert_eq!(settings["separator"].as_str(), Some("-"));
+        assert_eq!(settings["case"].as_str(), Some("lower"));
+        assert_eq!(settings["max-length"].as_integer(), Some(1024));
+        assert_eq!(settings["extension"].as_str(), Some("lowercase"));
+        assert!(!Preset::new().settings().contains_key("separator"));
+    }
+}
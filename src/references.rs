@@ -0,0 +1,426 @@
+use crate::errors::Result;
+use crate::filesystem::Filesystem;
+use crate::ignore::{IgnoreRules, IgnoreStack, is_always_ignored};
+use iocore::Path;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+/// `REFERENCE_REGEX` finds the places where text refers to paths: the
+/// targets of Markdown links and images, quoted strings such as HTML
+/// `href` and `src` attributes, and unquoted `href` and `src`
+/// attributes
+static REFERENCE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"\]\((?<link>[^)\n]*)\)|"(?<double>[^"\n]*)"|'(?<single>[^'\n]*)'|(?:href|src)=(?<bare>[^\s>"']+)"#)
+        .expect("REFERENCE_REGEX")
+});
+/// `LINK_REGEX` splits the target of a Markdown link into its path,
+/// possibly between angle brackets, and its optional title
+static LINK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^(?<path>\s*<[^>]*>|.*?)(?<title>\s+(?:"[^"]*"|'[^']*'))?\s*$"#)
+        .expect("LINK_REGEX")
+});
+
+/// `ReferenceRewriter` replaces the paths of renamed entries with their
+/// new paths inside the text files matching a set of globs, e.g.: links
+/// to `images/My Image.png` or `images/My%20Image.png` in Markdown files
+/// become links to `images/my-image.png`.
+///
+/// Only paths in links, quoted strings and `href` or `src` attributes
+/// are rewritten, and only when, resolved relative to the file they
+/// appear in, they lead to a renamed entry: the word "Notes" in a
+/// sentence is left alone even when a `Notes` directory was renamed, as
+/// are references to another file of the same name.
+#[derive(Clone, Debug)]
+pub struct ReferenceRewriter {
+    globs: Vec<String>,
+    /// new name of every renamed entry by its original path
+    names: BTreeMap<String, String>,
+    /// original path of every renamed entry by its new path
+    originals: BTreeMap<String, Path>,
+}
+impl ReferenceRewriter {
+    pub fn new<T: std::fmt::Display>(globs: impl IntoIterator<Item = T>) -> ReferenceRewriter {
+        ReferenceRewriter {
+            globs: globs.into_iter().map(|glob| glob.to_string()).collect(),
+            names: BTreeMap::new(),
+            originals: BTreeMap::new(),
+        }
+    }
+    /// `rename` registers the rename of the entry originally at
+    /// `original` into `to`
+    pub fn rename(&mut self, original: &Path, to: &Path) {
+        if original.to_string() == to.to_string() {
+            return;
+        }
+        self.names.insert(original.to_string(), to.name());
+        self.originals.insert(to.to_string(), original.clone());
+    }
+    /// `is_empty` is true when there is no glob or no entry to rewrite
+    /// references to
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_empty() || self.names.is_empty()
+    }
+    /// `original` is where the entry now at `path` was before it or
+    /// any of its parent directories got renamed
+    pub fn original(&self, path: &Path) -> Path {
+        let path = path.to_string();
+        let mut prefix = path.as_str();
+        loop {
+            if let Some(original) = self.originals.get(prefix) {
+                let below = path[prefix.len()..].trim_start_matches('/');
+                return if below.is_empty() {
+                    original.clone()
+                } else {
+                    original.join(below)
+                };
+            }
+            match prefix.rsplit_once('/') {
+                Some((parent, _)) if !parent.is_empty() => prefix = parent,
+                _ => return Path::new(&path),
+            }
+        }
+    }
+    /// `rewrite` returns the text of `file` with the references to
+    /// renamed entries replaced, `None` when it contains none of them
+    pub fn rewrite(&self, file: &Path, text: &str) -> Option<String> {
+        let original = self.original(file).to_string();
+        let directory = original.rsplit_once('/').map_or("", |(parent, _)| parent);
+        let mut rewritten = String::new();
+        let mut end = 0;
+        for captures in REFERENCE_REGEX.captures_iter(text) {
+            let (found, reference) = if let Some(link) = captures.name("link") {
+                let link = LINK_REGEX.captures(link.as_str()).and_then(|parts| {
+                    let path = parts.name("path")?;
+                    let trimmed = path.as_str().trim_start();
+                    let start = link.start() + path.start() + path.as_str().len() - trimmed.len();
+                    match trimmed
+                        .strip_prefix('<')
+                        .and_then(|path| path.strip_suffix('>'))
+                    {
+                        Some(path) => Some((start + 1, path)),
+                        None => Some((start, trimmed)),
+                    }
+                });
+                let Some((start, path)) = link else {
+                    continue;
+                };
+                (start..start + path.len(), path)
+            } else if let Some(found) = ["double", "single", "bare"]
+                .iter()
+                .find_map(|name| captures.name(name))
+            {
+                (found.range(), found.as_str())
+            } else {
+                continue;
+            };
+            let Some(new_reference) = self.rewrite_reference(directory, reference) else {
+                continue;
+            };
+            rewritten.push_str(&text[end..found.start]);
+            rewritten.push_str(&new_reference);
+            end = found.end;
+        }
+        if end == 0 {
+            return None;
+        }
+        rewritten.push_str(&text[end..]);
+        Some(rewritten)
+    }
+    /// `rewrite_reference` resolves `reference` relative to `directory`
+    /// and renames each of its components that leads to a renamed
+    /// entry, keeping them URL-encoded when they were, or returns
+    /// `None` when it leads to no renamed entry. URLs with a scheme and
+    /// fragments are left alone, queries and fragments are kept.
+    pub fn rewrite_reference(&self, directory: &str, reference: &str) -> Option<String> {
+        if reference.is_empty() || reference.starts_with('#') || reference.contains(':') {
+            return None;
+        }
+        let (path, suffix) =
+            reference.split_at(reference.find(['?', '#']).unwrap_or(reference.len()));
+        let mut current = if path.starts_with('/') {
+            String::new()
+        } else {
+            directory.to_string()
+        };
+        let mut renamed = false;
+        let mut components = Vec::<String>::new();
+        for component in path.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => {
+                    current = current
+                        .rsplit_once('/')
+                        .map_or(String::new(), |(parent, _)| parent.to_string());
+                }
+                _ => {
+                    let decoded = percent_decode(component);
+                    current = format!("{current}/{decoded}");
+                    if let Some(name) = self.names.get(&current) {
+                        renamed = true;
+                        components.push(if decoded != component {
+                            percent_encode(name)
+                        } else {
+                            name.clone()
+                        });
+                        continue;
+                    }
+                }
+            }
+            components.push(component.to_string());
+        }
+        renamed.then(|| format!("{}{suffix}", components.join("/")))
+    }
+    /// `files` returns the files at or below `root` whose path relative
+    /// to `base` matches the globs, sorted by path, leaving out the
+    /// entries `is_ignored` tells about given the ignore rules in
+    /// effect in their directory, starting with `ignores`
+    pub fn files<F: Filesystem>(
+        &self,
+        filesystem: &F,
+        base: &Path,
+        root: &Path,
+        ignores: &IgnoreStack,
+        is_ignored: impl Fn(&IgnoreStack, &Path, bool) -> bool,
+    ) -> Result<Vec<Path>> {
+        let rules = IgnoreRules::from_lines(base, &self.globs)?;
+        let mut files = Vec::new();
+        let mut entries = vec![(root.clone(), ignores.clone())];
+        while let Some((entry, ignores)) = entries.pop() {
+            let is_dir = filesystem.is_dir(&entry);
+            if is_always_ignored(&entry) || is_ignored(&ignores, &entry, is_dir) {
+                continue;
+            }
+            if is_dir {
+                let ignores = ignores.with_directory_on(filesystem, &entry)?;
+                for child in filesystem.list(&entry)? {
+                    entries.push((child, ignores.clone()));
+                }
+            } else if rules.matched(&entry, false) == Some(true) {
+                files.push(entry);
+            }
+        }
+        files.sort_by_key(|file| file.to_string());
+        Ok(files)
+    }
+    /// `rewrite_file` rewrites the references inside `path`, only
+    /// returning the diff of the change with `dry_run`. Files which are
+    /// not UTF-8 are left alone.
+    pub fn rewrite_file<F: Filesystem>(
+        &self,
+        filesystem: &F,
+        path: &Path,
+        dry_run: bool,
+    ) -> Result<Option<String>> {
+        let Ok(text) = String::from_utf8(filesystem.read(path)?) else {
+            return Ok(None);
+        };
+        let Some(rewritten) = self.rewrite(path, &text) else {
+            return Ok(None);
+        };
+        if !dry_run {
+            filesystem.write(path, rewritten.as_bytes())?;
+        }
+        Ok(Some(diff(path, &text, &rewritten)))
+    }
+}
+
+/// `diff` lists the lines that differ between `old` and `new`, which
+/// have as many lines since rewriting references never adds any
+pub fn diff(path: &Path, old: &str, new: &str) -> String {
+    let mut diff = format!("--- {path}\n+++ {path}\n");
+    for (number, (old, new)) in old.lines().zip(new.lines()).enumerate() {
+        if old != new {
+            diff.push_str(&format!("@@ {} @@\n-{old}\n+{new}\n", number + 1));
+        }
+    }
+    diff
+}
+
+/// `percent_encode` encodes every byte of `name` other than ASCII
+/// letters, digits, `-`, `.`, `_` and `~` as in URLs
+pub fn percent_encode(name: &str) -> String {
+    name.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// `percent_decode` reverses [`percent_encode`], leaving `text` as is
+/// when it does not decode into UTF-8
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let byte = text
+            .get(index + 1..index + 3)
+            .filter(|_| bytes[index] == b'%')
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match byte {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8(decoded).unwrap_or_else(|_| text.to_string())
+}
+
+#[cfg(test)]
+mod references_tests {
+    use crate::{
+        Filesystem, IgnoreRules, IgnoreStack, MemoryFilesystem, ReferenceRewriter, Result,
+        percent_decode, percent_encode,
+    };
+    use iocore::Path;
+
+    fn rewriter() -> ReferenceRewriter {
+        let mut rewriter = ReferenceRewriter::new(["*.md", "docs/**/*.html"]);
+        rewriter.rename(&Path::new("/site/Some Dir"), &Path::new("/site/some-dir"));
+        rewriter.rename(
+            &Path::new("/site/Some Dir/My Image.png"),
+            &Path::new("/site/some-dir/my-image.png"),
+        );
+        rewriter.rename(&Path::new("/site/Notes"), &Path::new("/site/notes"));
+        rewriter
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode("My Image.png"), "My%20Image.png");
+        assert_eq!(percent_encode("Ação.txt"), "A%C3%A7%C3%A3o.txt");
+        assert_eq!(percent_decode("A%C3%A7%C3%A3o.txt"), "Ação.txt");
+        assert_eq!(percent_decode("100%.txt"), "100%.txt");
+    }
+
+    #[test]
+    fn test_rewrite_replaces_links_and_quoted_paths() {
+        let rewriter = rewriter();
+        let file = Path::new("/site/index.md");
+        assert_eq!(
+            rewriter.rewrite(
+                &file,
+                "![](Some Dir/My%20Image.png) and <img src=\"Some Dir/My Image.png\">"
+            ),
+            Some("![](some-dir/my-image.png) and <img src=\"some-dir/my-image.png\">".to_string())
+        );
+        assert_eq!(
+            rewriter.rewrite(
+                &file,
+                "[notes](<Notes> \"Notes\") [top](./Notes/#top) [abs](/site/Notes?x=1)"
+            ),
+            Some(
+                "[notes](<notes> \"Notes\") [top](./notes/#top) [abs](/site/notes?x=1)".to_string()
+            )
+        );
+        assert_eq!(
+            rewriter.rewrite(&Path::new("/site/Some Dir/page.md"), "![](../Notes)"),
+            Some("![](../notes)".to_string())
+        );
+        assert_eq!(
+            rewriter.rewrite(
+                &file,
+                "[](Some Dir.bak/My Image.png) [](https://x.org/Notes)"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rewrite_leaves_prose_alone() {
+        assert_eq!(
+            rewriter().rewrite(
+                &Path::new("/site/index.md"),
+                "Notes are kept in Some Dir, see My Image.png\n"
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_rewrite_leaves_entries_of_the_same_name_elsewhere_alone() {
+        let rewriter = rewriter();
+        assert_eq!(
+            rewriter.rewrite(
+                &Path::new("/site/other/index.md"),
+                "![](Notes) ![](My Image.png)"
+            ),
+            None
+        );
+        assert_eq!(
+            rewriter.rewrite(&Path::new("/site/some-dir/index.md"), "![](My Image.png)"),
+            Some("![](my-image.png)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_rewrite_files_matching_globs() -> Result<()> {
+        let filesystem = MemoryFilesystem::new();
+        let root = Path::new("/site");
+        filesystem.write(&root.join("README.md"), b"# Title\n![](Notes)\n");
+        filesystem.write(
+            &root.join("docs/index.html"),
+            b"<img src=\"../Some%20Dir/My%20Image.png\">",
+        );
+        filesystem.write(&root.join("vendor/README.md"), b"![](../Notes)");
+        filesystem.write(&root.join("notes.txt"), b"![](Notes)");
+        let rewriter = rewriter();
+
+        let ignores = IgnoreStack::from_rules(IgnoreRules::from_lines(&root, ["vendor/"])?);
+        let files = rewriter.files(
+            &filesystem,
+            &root,
+            &root,
+            &ignores,
+            |ignores, path, is_dir| ignores.is_ignored(path, is_dir),
+        )?;
+        assert_eq!(
+            files
+                .iter()
+                .map(|file| file.to_string())
+                .collect::<Vec<_>>(),
+            vec!["/site/README.md", "/site/docs/index.html"]
+        );
+        assert_eq!(
+            rewriter.rewrite_file(&filesystem, &root.join("README.md"), true)?,
+            Some(
+                "--- /site/README.md\n+++ /site/README.md\n@@ 2 @@\n-![](Notes)\n+![](notes)\n"
+                    .to_string()
+            )
+        );
+        assert_eq!(
+            filesystem.read(&root.join("README.md"))?,
+            b"# Title\n![](Notes)\n"
+        );
+        rewriter.rewrite_file(&filesystem, &root.join("docs/index.html"), false)?;
+        assert_eq!(
+            filesystem.read(&root.join("docs/index.html"))?,
+            b"<img src=\"../some-dir/my-image.png\">"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_files_are_looked_up_at_their_original_paths() {
+        let rewriter = rewriter();
+        assert_eq!(
+            rewriter
+                .original(&Path::new("/site/some-dir/page.md"))
+                .to_string(),
+            "/site/Some Dir/page.md"
+        );
+        assert_eq!(
+            rewriter.rewrite(&Path::new("/site/some-dir/page.md"), "![](My%20Image.png)"),
+            Some("![](my-image.png)".to_string())
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use crate::errors::{Error, Result};
+use crate::executor::RenameOutcome;
+use crate::plan::{Conflict, RenameOperation, RenameReason};
+use clap::ValueEnum;
+use iocore::Path;
+use serde::Serialize;
+
+/// `OutputFormat` selects how `slugify-filenames` reports what it did
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// log lines meant to be read by people
+    #[default]
+    Human,
+    /// a single JSON array of rename records printed once done
+    Json,
+    /// one rename record per line printed as entries get processed
+    Jsonl,
+}
+impl OutputFormat {
+    pub fn is_structured(&self) -> bool {
+        *self != OutputFormat::Human
+    }
+}
+
+/// `RenameAction` is what happened to the entry of a [`RenameRecord`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RenameAction {
+    Renamed,
+    /// the entry would have been renamed were it not for `--dry-run`
+    WouldRename,
+    Unchanged,
+    /// the entry was left alone, e.g.: because of `--min-depth` or
+    /// `--on-conflict skip`
+    Skipped,
+    Ignored,
+    Error,
+}
+
+/// `RenameRecord` is the structured counterpart of the log lines
+/// printed for each entry
+#[derive(Clone, Debug, Serialize)]
+pub struct RenameRecord {
+    pub from: Path,
+    pub to: Path,
+    pub action: RenameAction,
+    pub reason: Option<RenameReason>,
+    pub conflict: Option<Conflict>,
+    pub error: Option<Error>,
+}
+impl RenameRecord {
+    /// `new` describes the `outcome` of carrying out `operation`
+    pub fn new(operation: &RenameOperation, outcome: &RenameOutcome) -> RenameRecord {
+        let (to, action) = match outcome {
+            RenameOutcome::Renamed(to) => (to.clone(), RenameAction::Renamed),
+            RenameOutcome::WouldRename(to) => (to.clone(), RenameAction::WouldRename),
+            RenameOutcome::Unchanged => (
+                operation.from.clone(),
+                match operation.reason {
+                    RenameReason::Ignored => RenameAction::Ignored,
+                    RenameReason::AboveMinDepth
+                    | RenameReason::ConflictSkipped
+                    | RenameReason::EmptySlug => RenameAction::Skipped,
+                    _ => RenameAction::Unchanged,
+                },
+            ),
+        };
+        RenameRecord {
+            from: operation.from.clone(),
+            to,
+            action,
+            reason: Some(operation.reason),
+            conflict: operation.conflict.clone(),
+            error: None,
+        }
+    }
+    /// `error` records the failure to rename `from` into `to`
+    pub fn error(
+        from: &Path,
+        to: &Path,
+        reason: Option<RenameReason>,
+        error: &Error,
+    ) -> RenameRecord {
+        RenameRecord {
+            from: from.clone(),
+            to: to.clone(),
+            action: RenameAction::Error,
+            reason,
+            conflict: None,
+            error: Some(error.clone()),
+        }
+    }
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+#[cfg(test)]
+mod report_tests {
+    use crate::{
+        Error, RenameAction, RenameOperation, RenameOutcome, RenameReason, RenameRecord, Result,
+    };
+    use iocore::Path;
+
+    fn operation(from: &str, to: &str, reason: RenameReason) -> RenameOperation {
+        RenameOperation {
+            from: Path::new(from),
+            to: Path::new(to),
+            original: Path::new(from),
+            is_dir: false,
+            depth: 0,
+            reason,
+            conflict: None,
+        }
+    }
+
+    #[test]
+    fn test_record_actions() {
+        let renamed = operation("/tmp/A B", "/tmp/A-B", RenameReason::Slugified);
+        assert_eq!(
+            RenameRecord::new(&renamed, &RenameOutcome::Renamed(Path::new("/tmp/A-B"))).action,
+            RenameAction::Renamed
+        );
+        assert_eq!(
+            RenameRecord::new(&renamed, &RenameOutcome::WouldRename(Path::new("/tmp/A-B"))).action,
+            RenameAction::WouldRename
+        );
+        for (reason, action) in [
+            (RenameReason::AlreadySlugified, RenameAction::Unchanged),
+            (RenameReason::Ignored, RenameAction::Ignored),
+            (RenameReason::AboveMinDepth, RenameAction::Skipped),
+        ] {
+            let operation = operation("/tmp/a", "/tmp/a", reason);
+            let record = RenameRecord::new(&operation, &RenameOutcome::Unchanged);
+            assert_eq!(record.action, action);
+            assert_eq!(record.to, Path::new("/tmp/a"));
+        }
+    }
+
+    #[test]
+    fn test_record_to_json() -> Result<()> {
+        let record = RenameRecord::error(
+            &Path::new("/tmp/A B"),
+            &Path::new("/tmp/A-B"),
+            Some(RenameReason::Slugified),
+            &Error::TargetExists {
+                from: Path::new("/tmp/A B"),
+                to: Path::new("/tmp/A-B"),
+            },
+        );
+        assert_eq!(
+            record.to_json()?,
+            r#"{"from":"/tmp/A B","to":"/tmp/A-B","action":"error","reason":"slugified","conflict":null,"error":{"variant":"TargetExists","message":"cannot rename /tmp/A B to /tmp/A-B: target already exists"}}"#
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,615 @@
+use crate::errors::{Error, Result};
+use crate::string::{
+    DEFAULT_SEPARATOR, SPECIAL_PATTERN_CHARS, UNNEEDED_UNIQUEFY_REGEX, list_of_trimmed_strings,
+};
+use any_ascii::any_ascii;
+use clap::ValueEnum;
+use heck::{
+    ToKebabCase, ToLowerCamelCase, ToPascalCase, ToShoutyKebabCase, ToShoutySnakeCase, ToSnakeCase,
+    ToTrainCase,
+};
+use iocore::Path;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::sync::LazyLock;
+use strip_ansi_escapes::strip as strip_ansi_escapes;
+
+/// characters kept verbatim by default, expressed as the body of a
+/// regex character class
+pub const DEFAULT_ALLOWED_CHARS: &str = "a-zA-Z0-9_.-";
+
+/// extensions made of more than one suffix which are kept together
+/// when splitting filenames, see [`Slugifier::split_extension`]
+pub const DEFAULT_COMPOUND_EXTENSIONS: &[&str] = &[
+    "tar.gz", "tar.bz2", "tar.xz", "tar.zst", "tar.lz", "tar.lzma", "tar.Z", "d.ts", "d.mts",
+    "d.cts", "min.js", "min.css", "js.map", "css.map", "user.js",
+];
+
+/// characters that cannot appear inside a filename: they would turn
+/// slugs into paths or cut them short
+pub const PATH_UNSAFE_CHARS: &[char] = &['/', '\0', std::path::MAIN_SEPARATOR];
+
+/// device names Windows reserves whatever the case and the extension,
+/// see [`SlugOptions::avoid_reserved_names`]
+pub const WINDOWS_RESERVED_NAMES: &[&str] = &[
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// `is_windows_reserved_name` tells whether `stem`, once stripped of
+/// trailing dots and spaces, is one of [`WINDOWS_RESERVED_NAMES`]
+pub fn is_windows_reserved_name(stem: &str) -> bool {
+    let stem = stem.trim_end_matches(['.', ' ']);
+    WINDOWS_RESERVED_NAMES
+        .iter()
+        .any(|name| name.eq_ignore_ascii_case(stem))
+}
+
+/// placeholder inserted in place of unsafe characters before the
+/// separator is known to be safe to insert, it never survives the
+/// allowed characters regex and therefore cannot clash with the input.
+const SEPARATOR_PLACEHOLDER: char = '\u{1f}';
+/// matches a special character adjacent to placeholders so that
+/// `"Foo - Bar"` or `"Test!.txt"` do not keep a dangling separator
+static SPECIAL_CHAR_SURROUNDED_BY_PLACEHOLDER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\x1f*(?<special>[_.-])\x1f*")
+        .expect("SPECIAL_CHAR_SURROUNDED_BY_PLACEHOLDER_REGEX")
+});
+
+/// `compile` is [`Regex::new`] failing with [`Error::InvalidPattern`]
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|error| Error::invalid_pattern(pattern, error))
+}
+
+/// `Case` is applied to slugs after transliteration. The word styles
+/// from `kebab` to `shouty-kebab` split slugs into words with `heck`
+/// and join them their own way regardless of
+/// [`SlugOptions::separator`].
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Case {
+    /// keep the case of the input
+    #[default]
+    Preserve,
+    /// lowercase the whole slug
+    Lower,
+    /// uppercase the whole slug
+    Upper,
+    /// words-joined-by-hyphens
+    Kebab,
+    /// words_joined_by_underscores
+    Snake,
+    /// wordsJoinedInCamelCase
+    Camel,
+    /// WordsJoinedInPascalCase
+    Pascal,
+    /// Words-Joined-In-Train-Case
+    Train,
+    /// WORDS_JOINED_BY_UNDERSCORES
+    ShoutySnake,
+    /// WORDS-JOINED-BY-HYPHENS
+    ShoutyKebab,
+}
+impl Case {
+    pub fn apply(&self, slug: &str) -> String {
+        match self {
+            Case::Preserve => slug.to_string(),
+            Case::Lower => slug.to_lowercase(),
+            Case::Upper => slug.to_uppercase(),
+            Case::Kebab => slug.to_kebab_case(),
+            Case::Snake => slug.to_snake_case(),
+            Case::Camel => slug.to_lower_camel_case(),
+            Case::Pascal => slug.to_pascal_case(),
+            Case::Train => slug.to_train_case(),
+            Case::ShoutySnake => slug.to_shouty_snake_case(),
+            Case::ShoutyKebab => slug.to_shouty_kebab_case(),
+        }
+    }
+    /// `apply_to_extension` only changes the case of the letters of
+    /// `extension`: uppercase for the upper and shouty styles,
+    /// lowercase for the other word styles
+    pub fn apply_to_extension(&self, extension: &str) -> String {
+        match self {
+            Case::Preserve => extension.to_string(),
+            Case::Upper | Case::ShoutySnake | Case::ShoutyKebab => extension.to_uppercase(),
+            _ => extension.to_lowercase(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExtensionHandling {
+    /// slugify the extension with the same options as the stem
+    #[default]
+    Slugify,
+    /// slugify the extension and lowercase it regardless of the case option
+    Lowercase,
+    /// leave the extension untouched
+    Preserve,
+}
+
+/// `SlugOptions` holds every knob of the slugification pipeline and
+/// builds a [`Slugifier`].
+///
+/// *Example*
+///
+/// ```
+/// use slugify_filenames::{Case, SlugOptions};
+///
+/// let slugifier = SlugOptions::new().separator("_").case(Case::Lower).build()?;
+/// assert_eq!(slugifier.slugify("Imagine  Thís string")?, "imagine_this_string");
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SlugOptions {
+    pub separator: String,
+    pub case: Case,
+    pub allowed_chars: String,
+    pub max_length: Option<usize>,
+    pub transliterate: bool,
+    pub extension: ExtensionHandling,
+    pub compound_extensions: Vec<String>,
+    /// literal `(from, to)` replacements applied in order before
+    /// anything else, e.g.: `("&", "and")`
+    pub replacements: Vec<(String, String)>,
+    /// keep clear of the device names Windows reserves, see
+    /// [`WINDOWS_RESERVED_NAMES`], and of trailing dots and spaces
+    pub avoid_reserved_names: bool,
+}
+impl Default for SlugOptions {
+    fn default() -> SlugOptions {
+        SlugOptions {
+            separator: DEFAULT_SEPARATOR.to_string(),
+            case: Case::default(),
+            allowed_chars: DEFAULT_ALLOWED_CHARS.to_string(),
+            max_length: None,
+            transliterate: true,
+            extension: ExtensionHandling::default(),
+            compound_extensions: DEFAULT_COMPOUND_EXTENSIONS
+                .iter()
+                .map(|extension| extension.to_string())
+                .collect(),
+            replacements: Vec::new(),
+            avoid_reserved_names: false,
+        }
+    }
+}
+impl SlugOptions {
+    pub fn new() -> SlugOptions {
+        SlugOptions::default()
+    }
+    pub fn separator<T: Display>(mut self, separator: T) -> SlugOptions {
+        self.separator = separator.to_string();
+        self
+    }
+    pub fn case(mut self, case: Case) -> SlugOptions {
+        self.case = case;
+        self
+    }
+    /// `allowed_chars` is the body of a regex character class, e.g.: `"a-z0-9_"`
+    pub fn allowed_chars<T: Display>(mut self, allowed_chars: T) -> SlugOptions {
+        self.allowed_chars = allowed_chars.to_string();
+        self
+    }
+    pub fn max_length(mut self, max_length: Option<usize>) -> SlugOptions {
+        self.max_length = max_length;
+        self
+    }
+    pub fn transliterate(mut self, transliterate: bool) -> SlugOptions {
+        self.transliterate = transliterate;
+        self
+    }
+    pub fn extension(mut self, extension: ExtensionHandling) -> SlugOptions {
+        self.extension = extension;
+        self
+    }
+    /// `compound_extensions` replaces the multi-part extensions
+    /// recognized by [`Slugifier::split_extension`], e.g.: `["tar.gz"]`
+    pub fn compound_extensions<T: Display>(
+        mut self,
+        compound_extensions: impl IntoIterator<Item = T>,
+    ) -> SlugOptions {
+        self.compound_extensions = compound_extensions
+            .into_iter()
+            .map(|extension| extension.to_string().trim_start_matches('.').to_string())
+            .collect();
+        self
+    }
+    /// `replace` replaces every occurrence of `from` with `to` before
+    /// slugifying, e.g.: `.replace("&", " and ")`
+    pub fn replace<T: Display, U: Display>(mut self, from: T, to: U) -> SlugOptions {
+        self.replacements.push((from.to_string(), to.to_string()));
+        self
+    }
+    /// `avoid_reserved_names` appends the separator to stems that are
+    /// device names Windows reserves, e.g.: `CON.txt` becomes
+    /// `CON-.txt`, and strips trailing dots and spaces from filenames
+    pub fn avoid_reserved_names(mut self, avoid_reserved_names: bool) -> SlugOptions {
+        self.avoid_reserved_names = avoid_reserved_names;
+        self
+    }
+    pub fn build(self) -> Result<Slugifier> {
+        Slugifier::new(self)
+    }
+}
+
+/// `Slugifier` applies a compiled set of [`SlugOptions`] to strings
+/// and filenames.
+#[derive(Clone, Debug)]
+pub struct Slugifier {
+    options: SlugOptions,
+    disallowed: Regex,
+    separator_run: Option<Regex>,
+}
+impl Slugifier {
+    /// `new` fails with [`Error::UsageError`] when the separator or the
+    /// allowed characters could produce any of [`PATH_UNSAFE_CHARS`]
+    pub fn new(options: SlugOptions) -> Result<Slugifier> {
+        if let Some(unsafe_char) = options
+            .separator
+            .chars()
+            .find(|c| PATH_UNSAFE_CHARS.contains(c))
+        {
+            return Err(Error::UsageError(format!(
+                "separator {:#?} contains {unsafe_char:#?} which cannot appear in filenames",
+                options.separator
+            )));
+        }
+        let allowed = compile(&format!("^[{}]$", options.allowed_chars))?;
+        if let Some(unsafe_char) = PATH_UNSAFE_CHARS
+            .iter()
+            .find(|c| allowed.is_match(&c.to_string()))
+        {
+            return Err(Error::UsageError(format!(
+                "allowed characters {:#?} include {unsafe_char:#?} which cannot appear in filenames",
+                options.allowed_chars
+            )));
+        }
+        let disallowed = compile(&format!("[^{}]+", options.allowed_chars))?;
+        let separator_run = if options.separator.is_empty() {
+            None
+        } else {
+            Some(compile(&format!(
+                "(?:{}){{2,}}",
+                regex::escape(&options.separator)
+            ))?)
+        };
+        Ok(Slugifier {
+            options,
+            disallowed,
+            separator_run,
+        })
+    }
+    pub fn options(&self) -> &SlugOptions {
+        &self.options
+    }
+    pub fn slugify<T: std::string::ToString>(&self, haystack: T) -> Result<String> {
+        let slug = self.case(&self.slugify_preserving_case(haystack)?);
+        Ok(self.truncate(&slug, self.options.max_length))
+    }
+    /// `slugify_filename` slugifies the stem and extension of a
+    /// filename separately according to [`ExtensionHandling`], only
+    /// truncating the stem when [`SlugOptions::max_length`] is set.
+    pub fn slugify_filename(&self, stem: &str, extension: Option<&str>) -> Result<String> {
+        let extension = match extension {
+            Some(extension) => Some(self.slugify_extension(extension)?),
+            None => None,
+        }
+        .filter(|extension| !extension.is_empty());
+        let stem = self.case(&self.slugify_preserving_case(stem)?);
+        let max_stem_length = self.options.max_length.map(|max_length| {
+            max_length.saturating_sub(extension.as_ref().map(|e| e.len() + 1).unwrap_or_default())
+        });
+        let stem = self.truncate(&stem, max_stem_length);
+        if self.options.avoid_reserved_names {
+            return Ok(self.avoid_reserved_name(&stem, extension.as_deref()));
+        }
+        Ok(match extension {
+            Some(extension) => format!("{stem}.{extension}"),
+            None => stem,
+        })
+    }
+    /// `avoid_reserved_name` joins `stem` and `extension` without the
+    /// trailing dots and spaces Windows drops, appending the separator
+    /// to a stem Windows reserves whatever its extension
+    fn avoid_reserved_name(&self, stem: &str, extension: Option<&str>) -> String {
+        let extension = extension
+            .map(|extension| extension.trim_end_matches(['.', ' ']))
+            .filter(|extension| !extension.is_empty());
+        let stem = match extension {
+            Some(_) => stem,
+            None => stem.trim_end_matches(['.', ' ']),
+        };
+        let stem = if is_windows_reserved_name(stem) {
+            format!("{stem}{}", self.options.separator)
+        } else {
+            stem.to_string()
+        };
+        match extension {
+            Some(extension) => format!("{stem}.{extension}"),
+            None => stem,
+        }
+    }
+    /// `split_extension` splits `filename` into its stem and extension,
+    /// the longest matching [`SlugOptions::compound_extensions`] taking
+    /// precedence over the last suffix, e.g.: `"archive.tar.gz"`
+    /// becomes `("archive", Some("tar.gz"))`
+    pub fn split_extension(&self, filename: &str) -> (String, Option<String>) {
+        let lowercase = filename.to_lowercase();
+        let compound = self
+            .options
+            .compound_extensions
+            .iter()
+            .filter(|extension| {
+                let suffix = format!(".{}", extension.to_lowercase());
+                lowercase.len() > suffix.len()
+                    && lowercase.ends_with(&suffix)
+                    && filename.is_char_boundary(filename.len() - suffix.len())
+            })
+            .max_by_key(|extension| extension.len());
+        match compound {
+            Some(extension) => {
+                let at = filename.len() - extension.len();
+                (
+                    filename[..at - 1].to_string(),
+                    Some(filename[at..].to_string()),
+                )
+            }
+            None => Path::new(filename).split_extension(),
+        }
+    }
+    pub fn slugify_extension(&self, extension: &str) -> Result<String> {
+        Ok(match self.options.extension {
+            ExtensionHandling::Slugify => self
+                .options
+                .case
+                .apply_to_extension(&self.slugify_preserving_case(extension)?),
+            ExtensionHandling::Lowercase => self.slugify_preserving_case(extension)?.to_lowercase(),
+            ExtensionHandling::Preserve => extension.to_string(),
+        })
+    }
+    fn case(&self, slug: &str) -> String {
+        self.options.case.apply(slug)
+    }
+    fn truncate(&self, slug: &str, max_length: Option<usize>) -> String {
+        match max_length {
+            Some(max_length) if slug.len() > max_length => {
+                let mut end = max_length;
+                while !slug.is_char_boundary(end) {
+                    end -= 1;
+                }
+                let truncated = self.trim_separator(&slug[..end]);
+                truncated
+                    .trim_end_matches(|c| SPECIAL_PATTERN_CHARS.contains(&c))
+                    .to_string()
+            }
+            _ => slug.to_string(),
+        }
+    }
+    fn trim_separator(&self, slug: &str) -> String {
+        if self.options.separator.is_empty() {
+            return slug.to_string();
+        }
+        slug.trim_start_matches(&self.options.separator)
+            .trim_end_matches(&self.options.separator)
+            .to_string()
+    }
+    fn slugify_preserving_case<T: std::string::ToString>(&self, haystack: T) -> Result<String> {
+        let mut stage0 = haystack.to_string();
+        for (from, to) in &self.options.replacements {
+            if !from.is_empty() {
+                stage0 = stage0.replace(from.as_str(), to);
+            }
+        }
+        let stage0_bytes = strip_ansi_escapes(&stage0);
+        let stage0_1 = String::from_utf8_lossy(&stage0_bytes);
+        let mut stage1_parts = list_of_trimmed_strings(stage0_1.split('\n')).join("\n");
+        for part in ["\t", "\\n", "\n"] {
+            stage1_parts = list_of_trimmed_strings(stage1_parts.split(part)).join("\n");
+        }
+        let stage1 = if self.options.transliterate {
+            any_ascii(&stage1_parts)
+        } else {
+            stage1_parts
+        };
+        let stage2 = self
+            .disallowed
+            .replace_all(&stage1, SEPARATOR_PLACEHOLDER.to_string())
+            .to_string();
+        let stage3 = UNNEEDED_UNIQUEFY_REGEX
+            .replace_all(&stage2, r".$extension")
+            .to_string();
+        let mut stage4 = stage3
+            .trim_matches(|c| c == SEPARATOR_PLACEHOLDER || SPECIAL_PATTERN_CHARS.contains(&c))
+            .to_string();
+        stage4 = SPECIAL_CHAR_SURROUNDED_BY_PLACEHOLDER_REGEX
+            .replace_all(&stage4, "$special")
+            .to_string();
+        for c in SPECIAL_PATTERN_CHARS {
+            let re = compile(&format!("[{}]{{2,}}", regex::escape(&c.to_string())))?;
+            stage4 = re.replace_all(&stage4, c.to_string()).to_string();
+        }
+        let stage5 = stage4.replace(SEPARATOR_PLACEHOLDER, &self.options.separator);
+        let stage6 = match &self.separator_run {
+            Some(re) => re
+                .replace_all(&stage5, self.options.separator.as_str())
+                .to_string(),
+            None => stage5,
+        };
+        Ok(self.trim_separator(&stage6))
+    }
+}
+
+#[cfg(test)]
+mod slugifier_tests {
+    use crate::{Case, Error, ExtensionHandling, Result, SlugOptions};
+
+    #[test]
+    fn test_slug_options_default_matches_slugify_string() -> Result<()> {
+        let slugifier = SlugOptions::new().build()?;
+        assert_eq!(slugifier.slugify("  Foo  Baz  ")?, "Foo-Baz");
+        assert_eq!(
+            slugifier.slugify("Imagine Thís string, àscii safê")?,
+            "Imagine-This-string-ascii-safe"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_slug_options_reject_path_separators() {
+        for separator in ["/", "-/-", "\0"] {
+            let error = SlugOptions::new().separator(separator).build().unwrap_err();
+            assert!(matches!(error, Error::UsageError(_)), "{separator:#?}");
+            assert_eq!(error.exit_code(), crate::EXIT_USAGE);
+        }
+        // `+-9` spans `/`
+        for allowed_chars in ["a-z/", "+-9", r"\x00-\x7f", r"\s\S"] {
+            let error = SlugOptions::new()
+                .allowed_chars(allowed_chars)
+                .build()
+                .unwrap_err();
+            assert!(matches!(error, Error::UsageError(_)), "{allowed_chars:#?}");
+        }
+        assert!(SlugOptions::new().separator("_").build().is_ok());
+    }
+
+    #[test]
+    fn test_slug_options_case() -> Result<()> {
+        let slugifier = SlugOptions::new().case(Case::Lower).build()?;
+        assert_eq!(slugifier.slugify("Foo Baz")?, "foo-baz");
+        let slugifier = SlugOptions::new().case(Case::Upper).build()?;
+        assert_eq!(slugifier.slugify("Foo Baz")?, "FOO-BAZ");
+        Ok(())
+    }
+
+    #[test]
+    fn test_slug_options_word_cases() -> Result<()> {
+        let cases = [
+            (Case::Kebab, "my-cafe-report-v2.pdf"),
+            (Case::Snake, "my_cafe_report_v2.pdf"),
+            (Case::Camel, "myCafeReportV2.pdf"),
+            (Case::Pascal, "MyCafeReportV2.pdf"),
+            (Case::Train, "My-Cafe-Report-V2.pdf"),
+            (Case::ShoutySnake, "MY_CAFE_REPORT_V2.PDF"),
+            (Case::ShoutyKebab, "MY-CAFE-REPORT-V2.PDF"),
+        ];
+        for (case, expected) in cases {
+            let slugifier = SlugOptions::new().case(case).build()?;
+            assert_eq!(
+                slugifier.slugify_filename("My Café reportV2", Some("Pdf"))?,
+                expected,
+                "{case:?}"
+            );
+        }
+        let slugifier = SlugOptions::new().case(Case::Snake).build()?;
+        assert_eq!(
+            slugifier.slugify("Imagine Thís string")?,
+            "imagine_this_string"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_slug_options_allowed_chars() -> Result<()> {
+        let slugifier = SlugOptions::new().allowed_chars("a-z0-9").build()?;
+        assert_eq!(slugifier.slugify("foo_bar.Baz")?, "foo-bar-az");
+        assert!(SlugOptions::new().allowed_chars("z-a").build().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_slug_options_max_length() -> Result<()> {
+        let slugifier = SlugOptions::new().max_length(Some(8)).build()?;
+        assert_eq!(slugifier.slugify("foo bar baz")?, "foo-bar");
+        assert_eq!(slugifier.slugify("foo")?, "foo");
+        assert_eq!(
+            slugifier.slugify_filename("foo bar baz", Some("txt"))?,
+            "foo.txt"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_slug_options_transliterate() -> Result<()> {
+        let slugifier = SlugOptions::new().transliterate(false).build()?;
+        assert_eq!(slugifier.slugify("café au lait")?, "caf-au-lait");
+        let slugifier = SlugOptions::new()
+            .transliterate(false)
+            .allowed_chars(r"\p{L}0-9_.-")
+            .build()?;
+        assert_eq!(slugifier.slugify("café au lait")?, "café-au-lait");
+        Ok(())
+    }
+
+    #[test]
+    fn test_slug_options_extension_handling() -> Result<()> {
+        let slugifier = SlugOptions::new().build()?;
+        assert_eq!(
+            slugifier.slugify_filename("My File", Some("TXT"))?,
+            "My-File.TXT"
+        );
+        let slugifier = SlugOptions::new()
+            .extension(ExtensionHandling::Lowercase)
+            .build()?;
+        assert_eq!(
+            slugifier.slugify_filename("My File", Some("TXT"))?,
+            "My-File.txt"
+        );
+        let slugifier = SlugOptions::new()
+            .case(Case::Lower)
+            .extension(ExtensionHandling::Preserve)
+            .build()?;
+        assert_eq!(
+            slugifier.slugify_filename("My File", Some("T X"))?,
+            "my-file.T X"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_slug_options_replacements() -> Result<()> {
+        let slugifier = SlugOptions::new()
+            .replace("&", " and ")
+            .replace("@", "at")
+            .build()?;
+        assert_eq!(
+            slugifier.slugify("Tom&Jerry @ Home")?,
+            "Tom-and-Jerry-at-Home"
+        );
+        assert_eq!(
+            slugifier.slugify_filename("R&D", Some("txt"))?,
+            "R-and-D.txt"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_compound_extensions() -> Result<()> {
+        let slugifier = SlugOptions::new().build()?;
+        for (filename, stem, extension) in [
+            ("archive.tar.gz", "archive", Some("tar.gz")),
+            ("Archive.TAR.GZ", "Archive", Some("TAR.GZ")),
+            ("types.d.ts", "types", Some("d.ts")),
+            ("app.min.js", "app", Some("min.js")),
+            ("notes.txt", "notes", Some("txt")),
+            ("tar.gz", "tar", Some("gz")),
+            ("README", "README", None),
+        ] {
+            assert_eq!(
+                slugifier.split_extension(filename),
+                (stem.to_string(), extension.map(String::from)),
+                "{filename}"
+            );
+        }
+        let slugifier = SlugOptions::new()
+            .compound_extensions([".tar.gz"])
+            .build()?;
+        assert_eq!(
+            slugifier.split_extension("app.min.js"),
+            ("app.min".to_string(), Some("js".to_string()))
+        );
+        Ok(())
+    }
+}
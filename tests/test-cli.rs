@@ -1,165 +1,282 @@
-#![allow(unused)]
 use iocore::Path;
-use iocore::{walk_dir, NoopProgressHandler};
-use iocore_test::test_name;
-use slugify::cli::SlugifyFilenames;
-use slugify::errors::Result;
+use slugify_filenames::cli::parse_configured;
+use slugify_filenames::{
+    EXIT_CHECK_FAILED, EXIT_PARTIAL_FAILURE, EXIT_USAGE, Error, Filesystem, MemoryFilesystem,
+    Result, SlugifyFilenames,
+};
+
+fn names(paths: Vec<Path>) -> Vec<String> {
+    paths.iter().map(|path| path.to_string()).collect()
+}
+
+/// `slugify_filenames` parses `args` as the command line does and
+/// runs them on `filesystem`
+fn slugify_filenames(filesystem: &MemoryFilesystem, args: &[&str]) -> Result<()> {
+    let args = std::iter::once("slugify-filenames")
+        .chain(args.iter().copied())
+        .map(String::from)
+        .collect();
+    let (cli, _) = parse_configured::<SlugifyFilenames>(args)?;
+    cli.run_on(filesystem)
+}
 
 #[test]
 fn test_slugify_filenames_recursive_files() -> Result<()> {
-    setup!();
-    path_to_test_file!("recursive/ filename with spaces ").write_unchecked(&[]);
-    path_to_test_file!("recursive/filename_with-Special!CharacteR$").write_unchecked(&[]);
-    path_to_test_file!("recursive/ filename with spaces and Special # CharacteR$ ")
-        .write_unchecked(&[]);
-
-    let test_path = folder_path!().name();
-    assert_eq!(&test_path, "test_slugify_filenames_recursive_files");
-
-    let file_list = path_to_test_file!("recursive").list()?;
-    assert!(
-        file_list.len() > 1,
-        "expected length > 1 for {file_list:#?}"
+    let filesystem = MemoryFilesystem::new();
+    let root = Path::new("/virtual/recursive");
+    filesystem.write(&root.join(" filename with spaces .txt"), &[]);
+    filesystem.write(&root.join("filename_with-Special!CharacteR$.txt"), &[]);
+    filesystem.write(
+        &root.join(" filename with spaces and Special # CharacteR$ .txt"),
+        &[],
     );
+
+    slugify_filenames(
+        &filesystem,
+        &["-r", "-l", "--no-journal", "/virtual/recursive"],
+    )?;
+
     assert_eq!(
-        file_list[0],
-        Path::new("tests/recursive/ filename with spaces and Special # CharacteR$ . .txt "),
+        names(filesystem.paths(&root)),
+        vec![
+            "/virtual/recursive/filename-with-spaces-and-special-character.txt",
+            "/virtual/recursive/filename-with-spaces.txt",
+            "/virtual/recursive/filename_with-special-character.txt",
+        ]
     );
-    assert_eq!(
-        file_list[1],
-        Path::new("tests/recursive/filename_with-Special!CharacteR$.txt"),
+    Ok(())
+}
+
+#[test]
+fn test_slugify_filenames_recursive_files_and_folders() -> Result<()> {
+    let filesystem = MemoryFilesystem::new();
+    let root = Path::new("/virtual/recursive files");
+    filesystem.write(&root.join(" and folders/ filename with spaces .txt"), &[]);
+    filesystem.write(
+        &root.join(" and folders/filename_with-Special!CharacteR$.txt"),
+        &[],
     );
+
+    slugify_filenames(
+        &filesystem,
+        &[
+            "--recursive",
+            "--lowercase",
+            "--no-journal",
+            "/virtual/recursive files",
+        ],
+    )?;
+
+    assert!(!filesystem.exists(&root));
     assert_eq!(
-        file_list[2],
-        Path::new("tests/recursive/ filename with spaces .txt "),
+        names(filesystem.paths(&Path::new("/virtual"))),
+        vec![
+            "/virtual/recursive-files",
+            "/virtual/recursive-files/and-folders",
+            "/virtual/recursive-files/and-folders/filename-with-spaces.txt",
+            "/virtual/recursive-files/and-folders/filename_with-special-character.txt",
+        ]
     );
+    Ok(())
+}
+
+#[test]
+fn test_slugify_filenames_without_recursion_renames_the_given_paths_only() -> Result<()> {
+    let filesystem = MemoryFilesystem::new();
+    let root = Path::new("/virtual/shallow");
+    filesystem.write(&root.join("Some Folder/Some File.txt"), &[]);
 
-    SlugifyFilenames::execute(vec![String::from("slugify-filenames"), test_path])?;
+    slugify_filenames(
+        &filesystem,
+        &["-l", "--no-journal", "/virtual/shallow/Some Folder"],
+    )?;
 
-    let file_list = folder_path!("recursive").list()?;
     assert_eq!(
-        file_list[0],
-        Path::new("tests/recursive/filename-with-spaces-and-special-character.txt"),
+        names(filesystem.paths(&root)),
+        vec![
+            "/virtual/shallow/some-folder",
+            "/virtual/shallow/some-folder/Some File.txt",
+        ]
     );
+    Ok(())
+}
+
+#[test]
+fn test_slugify_filenames_case_only_rename_on_case_insensitive_filesystem() -> Result<()> {
+    let filesystem = MemoryFilesystem::case_insensitive();
+    let root = Path::new("/virtual/case");
+    filesystem.write(&root.join("README.md"), b"contents");
+
+    slugify_filenames(
+        &filesystem,
+        &["--case", "lower", "--no-journal", "/virtual/case/README.md"],
+    )?;
+
     assert_eq!(
-        file_list[1],
-        Path::new("tests/recursive/filename_with-special-character.txt"),
+        names(filesystem.paths(&root)),
+        vec!["/virtual/case/readme.md"]
     );
+    assert_eq!(filesystem.read(&root.join("readme.md"))?, b"contents");
+    Ok(())
+}
+
+#[test]
+fn test_slugify_filenames_honors_ignore_patterns_and_files() -> Result<()> {
+    // --ignore patterns are relative to the current directory
+    let filesystem = MemoryFilesystem::new();
+    let root = Path::cwd().try_canonicalize().join("ignored");
+    filesystem.write(&root.join("Keep Me.KEEP"), &[]);
+    filesystem.write(&root.join("Vendor Code/Some File.txt"), &[]);
+    filesystem.write(&root.join("Rename Me.txt"), &[]);
+    filesystem.write(&root.join(".slugifyignore"), b"Vendor Code/\n");
+
+    slugify_filenames(
+        &filesystem,
+        &[
+            "-r",
+            "-l",
+            "--no-journal",
+            "--ignore",
+            "ignored/*.KEEP",
+            "--slugify-ignore",
+            &root.join(".slugifyignore").to_string(),
+            &root.to_string(),
+        ],
+    )?;
+
     assert_eq!(
-        file_list[2],
-        Path::new("tests/recursive/filename-with-spaces.txt"),
+        names(filesystem.paths(&root)),
+        [
+            ".slugifyignore",
+            "Keep Me.KEEP",
+            "Vendor Code",
+            "Vendor Code/Some File.txt",
+            "rename-me.txt",
+        ]
+        .map(|name| root.join(name).to_string())
     );
     Ok(())
 }
 
 #[test]
-fn test_slugify_filenames_recursive_files_and_folders() -> Result<()> {
-    setup!();
-    path_to_test_file!("recursive files/ and folders/ filename with spaces ").write_unchecked(&[]);
-    path_to_test_file!("recursive files/ and folders/filename_with-Special!CharacteR$")
-        .write_unchecked(&[]);
-    path_to_test_file!(
-        "recursive files/ and folders/ filename with spaces and Special # CharacteR$ "
-    )
-    .write_unchecked(&[]);
+fn test_slugify_filenames_applies_presets() -> Result<()> {
+    let filesystem = MemoryFilesystem::new();
+    let root = Path::new("/virtual/preset");
+    filesystem.write(&root.join("Some Module.PY"), &[]);
+
+    slugify_filenames(
+        &filesystem,
+        &[
+            "-r",
+            "--preset",
+            "python",
+            "--no-journal",
+            "/virtual/preset",
+        ],
+    )?;
 
-    let test_path = folder_path!().name();
     assert_eq!(
-        &test_path,
-        "test_slugify_filenames_recursive_files_and_folders"
+        names(filesystem.paths(&root)),
+        vec!["/virtual/preset/some_module.py"]
     );
+    Ok(())
+}
 
-    assert!(
-        folder_path!("recursive files").exists(),
-        "the path \"recursive files\" should exist"
-    );
-    assert!(
-        folder_path!("recursive files/ and folders").exists(),
-        "the path \"recursive files/ and folders\" should exist"
-    );
-    let file_list = path_to_test_file!("recursive files/ and folders/").list()?;
+#[test]
+fn test_slugify_filenames_journal_and_undo() -> Result<()> {
+    let filesystem = MemoryFilesystem::new();
+    let root = Path::new("/virtual/journaled");
+    filesystem.write(&root.join("Some Folder/Some File.txt"), b"contents");
+    let journal = "/virtual/journal.jsonl";
 
-    assert!(
-        file_list.len() > 1,
-        "expected length > 1 for {file_list:#?}"
-    );
+    slugify_filenames(
+        &filesystem,
+        &["-r", "-l", "--journal", journal, "/virtual/journaled"],
+    )?;
     assert_eq!(
-        file_list[2],
-        Path::new("tests/recursive files/ and folders/ filename with spaces .txt "),
+        names(filesystem.paths(&root)),
+        vec![
+            "/virtual/journaled/some-folder",
+            "/virtual/journaled/some-folder/some-file.txt",
+        ]
     );
+    assert!(filesystem.exists(&Path::new(journal)));
+
+    slugify_filenames(&filesystem, &["--undo", journal])?;
     assert_eq!(
-        file_list[0],
-        Path::new("tests/recursive files/ and folders/ filename with spaces and Special # CharacteR$ . .txt "),
+        names(filesystem.paths(&root)),
+        vec![
+            "/virtual/journaled/Some Folder",
+            "/virtual/journaled/Some Folder/Some File.txt",
+        ]
     );
     assert_eq!(
-        file_list[1],
-        Path::new("tests/recursive files/ and folders/filename_with-Special!CharacteR$.txt"),
+        filesystem.read(&root.join("Some Folder/Some File.txt"))?,
+        b"contents"
     );
+    Ok(())
+}
 
-    SlugifyFilenames::execute(vec![String::from("slugify-filenames"), test_path])?;
+#[test]
+fn test_slugify_filenames_check_exits_without_renaming() -> Result<()> {
+    let filesystem = MemoryFilesystem::new();
+    let root = Path::new("/virtual/check");
+    filesystem.write(&root.join("Some File.txt"), &[]);
+    filesystem.write(&root.join("already-slugified.txt"), &[]);
 
-    assert!(
-        folder_path!("recursive-files").exists(),
-        "the path \"recursive-files\" should exist"
-    );
-    assert!(
-        folder_path!("recursive-files/and-folders").exists(),
-        "the path \"recursive-files/and-folders\" should exist"
-    );
-    let file_list = folder_path!("recursive-files/and-folders").list()?;
-    assert_eq!(
-        file_list[0],
-        Path::new(
-            "tests/recursive-files/and-folders/filename-with-spaces-and-special-character.txt"
-        ),
-    );
-    assert_eq!(
-        file_list[1],
-        Path::new("tests/recursive-files/and-folders/filename_with-special-character.txt")
-    );
+    let error = slugify_filenames(&filesystem, &["-r", "--check", "/virtual/check"])
+        .expect_err("--check should fail when an entry would be renamed");
+
+    assert!(matches!(error, Error::CheckFailed { count: 1 }));
+    assert_eq!(error.exit_code(), EXIT_CHECK_FAILED);
     assert_eq!(
-        file_list[2],
-        Path::new("tests/recursive-files/and-folders/filename-with-spaces.txt")
+        names(filesystem.paths(&root)),
+        vec![
+            "/virtual/check/Some File.txt",
+            "/virtual/check/already-slugified.txt",
+        ]
     );
     Ok(())
 }
 
-#[macro_export]
-macro_rules! folder_path {
-    () => {{
-        Path::new(env!("CARGO_MANIFEST_DIR"))
-            .join("tests")
-            .join("fixtures")
-            .join(Path::new(file!()).without_extension().name())
-            .join(test_name!())
-    }};
-    ($name:expr $(,)?) => {{
-        let path = $crate::folder_path!();
-        let path = path.join($name);
-        path.mkdir_unchecked();
-        path
-    }};
-}
-#[macro_export]
-macro_rules! path_to_test_file {
-    ($name:expr $(,)?) => {
-        $crate::folder_path!().join($name)
-    };
+#[test]
+fn test_slugify_filenames_keep_going_exits_with_partial_failure() -> Result<()> {
+    let filesystem = MemoryFilesystem::new();
+    let root = Path::new("/virtual/keep-going");
+    filesystem.write(&root.join("Some File.txt"), &[]);
+    filesystem.write(&root.join("some-file.txt"), &[]);
+    filesystem.write(&root.join("Other File.txt"), &[]);
+
+    let error = slugify_filenames(
+        &filesystem,
+        &[
+            "-r",
+            "-l",
+            "--no-journal",
+            "--keep-going",
+            "--on-conflict",
+            "error",
+            "/virtual/keep-going",
+        ],
+    )
+    .expect_err("the colliding entry should fail");
+
+    assert_eq!(error.exit_code(), EXIT_PARTIAL_FAILURE);
+    assert_eq!(
+        names(filesystem.paths(&root)),
+        vec![
+            "/virtual/keep-going/Some File.txt",
+            "/virtual/keep-going/other-file.txt",
+            "/virtual/keep-going/some-file.txt",
+        ]
+    );
+    Ok(())
 }
 
-#[macro_export]
-macro_rules! setup {
-    () => {{
-        let folders = vec![
-            folder_path!("recursive"),
-            folder_path!("recursive files"),
-            folder_path!("recursive-files"),
-        ];
-
-        for folder in folders {
-            match folder.delete() {
-                Ok(_) | Err(_) => {}
-            }
-        }
-    }};
+#[test]
+fn test_slugify_filenames_invalid_arguments_are_usage_errors() {
+    let filesystem = MemoryFilesystem::new();
+    let error = slugify_filenames(&filesystem, &["--on-conflict", "bogus", "/virtual"])
+        .expect_err("an unknown conflict strategy should be refused");
+    assert_eq!(error.exit_code(), EXIT_USAGE);
 }